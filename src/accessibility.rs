@@ -0,0 +1,66 @@
+use bevy::a11y::accesskit::{NodeBuilder, Role};
+use bevy::a11y::AccessibilityNode;
+use bevy::prelude::*;
+
+use crate::Link;
+
+// <img alt="...">, an empty alt marks the image as decorative, so it's left out of the accessibility tree
+#[derive(Component)]
+pub(crate) struct Alt(pub(crate) String);
+
+// <a aria-label="...">, which replaces the name computed from the link's contents
+#[derive(Component)]
+pub(crate) struct AriaLabel(pub(crate) String);
+
+// the AccessKit node an element starts with, for the elements which bevy_ui doesn't already describe
+// (bevy_ui makes every Text a label and every Button a button, see links() for why links are special)
+pub(crate) fn node(role: Role, name: &str) -> AccessibilityNode {
+    let mut node = NodeBuilder::new(role);
+    if !name.is_empty() { node.set_name(name); }
+    AccessibilityNode::from(node)
+}
+
+// an inline link inside of a Text, see html::flatten
+pub(crate) fn inline_link(name: &str, href: &str) -> AccessibilityNode {
+    let mut link = node(Role::Link, name);
+    link.set_url(href);
+    link
+}
+
+// bevy_ui describes every Button as a button, named after the Text directly inside of it, whenever its
+// AccessibilityNode is added. Links are Buttons, so turn them back into links, with their href, named by their
+// aria-label or everything inside of them, including the alt of images
+pub(crate) fn links(
+    mut links: Query<(Entity, &Link, Option<&AriaLabel>, &mut AccessibilityNode), (With<Button>, Changed<AccessibilityNode>)>,
+    children: Query<&Children>,
+    texts: Query<&Text>,
+    alts: Query<&Alt>,
+) {
+    for (entity, link, aria_label, mut node) in links.iter_mut() {
+        let name = match aria_label {
+            Some(aria_label) => aria_label.0.clone(),
+            None => name(entity, &children, &texts, &alts),
+        };
+
+        node.set_role(Role::Link);
+        node.set_url(link.href.as_str());
+        if name.is_empty() { node.clear_name(); } else { node.set_name(name); }
+    }
+}
+
+// the text and alt text of an element and its descendants, in document order
+fn name(entity: Entity, children: &Query<&Children>, texts: &Query<&Text>, alts: &Query<&Alt>) -> String {
+    let mut parts = vec![];
+
+    if let Ok(text) = texts.get(entity) {
+        parts.push(text.sections.iter().map(|section| section.value.as_str()).collect::<String>());
+    }
+    if let Ok(alt) = alts.get(entity) {
+        parts.push(alt.0.clone());
+    }
+    for child in children.get(entity).into_iter().flatten() {
+        parts.push(name(*child, children, texts, alts));
+    }
+
+    parts.iter().map(|part| part.trim()).filter(|part| !part.is_empty()).collect::<Vec<&str>>().join(" ")
+}
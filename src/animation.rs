@@ -0,0 +1,392 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::window::RequestRedraw;
+
+use crate::computed::ComputedStyle;
+use crate::css::{self, CSS, Drawable, Styles};
+use crate::declarations::{self, Declaration};
+use crate::patch::Patch;
+use crate::stylesheet;
+use crate::text::{self, InlineSections};
+use crate::transition::{self, CubicBezier, Transitions, Value};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Direction {
+    #[default]
+    Normal,
+    Reverse,
+    Alternate,
+    AlternateReverse,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum FillMode {
+    #[default]
+    None,
+    Forwards,
+    Backwards,
+    Both,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum PlayState {
+    #[default]
+    Running,
+    Paused,
+}
+
+// animation-name, -duration, -timing-function, -delay, -iteration-count, -direction, -fill-mode and -play-state,
+// None means "not declared", the lists are matched up with animation-name by index like transition-*
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct AnimationProperties {
+    pub(crate) name: Option<Vec<String>>,
+    pub(crate) duration: Option<Vec<f32>>, // in seconds
+    pub(crate) timing_function: Option<Vec<CubicBezier>>,
+    pub(crate) delay: Option<Vec<f32>>, // in seconds
+    pub(crate) iteration_count: Option<Vec<f32>>, // infinite is f32::INFINITY
+    pub(crate) direction: Option<Vec<Direction>>,
+    pub(crate) fill_mode: Option<Vec<FillMode>>,
+    pub(crate) play_state: Option<Vec<PlayState>>,
+}
+
+impl Patch for AnimationProperties {
+    fn apply(&self, patch: &Self) -> Self {
+        AnimationProperties {
+            name: patch.name.clone().or(self.name.clone()),
+            duration: patch.duration.clone().or(self.duration.clone()),
+            timing_function: patch.timing_function.clone().or(self.timing_function.clone()),
+            delay: patch.delay.clone().or(self.delay.clone()),
+            iteration_count: patch.iteration_count.clone().or(self.iteration_count.clone()),
+            direction: patch.direction.clone().or(self.direction.clone()),
+            fill_mode: patch.fill_mode.clone().or(self.fill_mode.clone()),
+            play_state: patch.play_state.clone().or(self.play_state.clone()),
+        }
+    }
+}
+
+impl AnimationProperties {
+    // the timing of the index-th animation-name, lists which are too short repeat
+    fn of(&self, index: usize) -> Timing {
+        fn nth<T: Copy>(list: &Option<Vec<T>>, index: usize, initial: T) -> T {
+            list.as_ref().and_then(|each| each.get(index % each.len())).copied().unwrap_or(initial)
+        }
+
+        Timing {
+            duration: nth(&self.duration, index, 0.),
+            easing: nth(&self.timing_function, index, CubicBezier::EASE),
+            delay: nth(&self.delay, index, 0.),
+            iterations: nth(&self.iteration_count, index, 1.),
+            direction: nth(&self.direction, index, Direction::Normal),
+            fill_mode: nth(&self.fill_mode, index, FillMode::None),
+            play_state: nth(&self.play_state, index, PlayState::Running),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Timing {
+    pub(crate) duration: f32,
+    pub(crate) easing: CubicBezier,
+    pub(crate) delay: f32,
+    pub(crate) iterations: f32,
+    pub(crate) direction: Direction,
+    pub(crate) fill_mode: FillMode,
+    pub(crate) play_state: PlayState,
+}
+
+// a single `50% { ... }` of a @keyframes rule, which is cascaded on top of whichever element it animates
+pub(crate) struct Keyframe {
+    pub(crate) offset: f32, // 0 to 1
+    pub(crate) rule: String, // e.g. "@keyframes pulse 50%", for diagnostics and ComputedStyle::sources
+    pub(crate) declarations: Vec<Declaration>,
+    pub(crate) styles: Styles,
+}
+
+// the keyframes of a @keyframes rule, sorted by offset
+pub(crate) struct Keyframes(pub(crate) Vec<Keyframe>);
+
+impl Keyframes {
+    // the block of e.g. `@keyframes pulse { from { ... } 50% { ... } }`, which starts at `line` in its file
+    pub(crate) fn parse(css: &CSS, rule: &str, line: usize, block: &str) -> Self {
+        let mut keyframes = vec![];
+
+        for each in stylesheet::parse(block) {
+            let line = line + each.line - 1;
+            let declarations = declarations::parse(&each.block).into_iter()
+                .map(|declaration| Declaration { line: line + declaration.line - 1, ..declaration })
+                .collect::<Vec<Declaration>>();
+
+            // e.g. `0%, 100% { ... }`
+            for selector in each.prelude.split(',').map(str::trim) {
+                let offset = match selector {
+                    "from" => Some(0.),
+                    "to" => Some(1.),
+                    _ => selector.strip_suffix('%')
+                        .and_then(|percent| percent.parse::<f32>().ok())
+                        .map(|percent| percent / 100.)
+                        .filter(|offset| (0. ..=1.).contains(offset)),
+                };
+
+                match offset {
+                    Some(offset) => {
+                        let rule = format!("{} {}", rule, selector);
+                        let styles = declarations::styles(css, &rule, &declarations);
+                        keyframes.push(Keyframe { offset, rule, declarations: declarations.clone(), styles });
+                    }
+                    None => warn!("{}, line {}: invalid keyframe selector `{}`", rule, line, selector),
+                }
+            }
+        }
+
+        // a stable sort, so that the last of several keyframes with the same offset wins
+        keyframes.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+        Keyframes(keyframes)
+    }
+
+    // the value of every animated property at each keyframe which declares it, sorted by offset, with the
+    // element's own value at 0% and 100% unless a keyframe says otherwise. `cascade` styles the element with a
+    // keyframe's declarations as the last rule
+    fn properties(
+        &self,
+        css: &CSS,
+        computed: &ComputedStyle,
+        mut cascade: impl FnMut(&Keyframe) -> ComputedStyle,
+    ) -> Vec<(&'static str, Vec<(f32, Value)>)> {
+        let mut properties: Vec<(&'static str, Vec<(f32, Value)>)> = vec![];
+
+        for keyframe in self.0.iter() {
+            // whatever it declares, even when that's the initial value, e.g. opacity: 1 or transform: none
+            let declared = declarations::longhands(css, &keyframe.declarations);
+            let style = cascade(keyframe);
+
+            for property in transition::properties() {
+                if !declared.iter().any(|each| each == property) { continue; }
+                let Some(value) = transition::get(&style, property) else { continue; };
+
+                match properties.iter_mut().find(|(each, _)| *each == property) {
+                    Some((_, frames)) => frames.push((keyframe.offset, value)),
+                    None => properties.push((property, vec![(keyframe.offset, value)])),
+                }
+            }
+        }
+
+        for (property, frames) in properties.iter_mut() {
+            let Some(value) = transition::get(computed, property) else { continue; };
+            if frames.first().map_or(true, |(offset, _)| *offset > 0.) { frames.insert(0, (0., value.clone())); }
+            if frames.last().map_or(true, |(offset, _)| *offset < 1.) { frames.push((1., value)); }
+        }
+
+        properties
+    }
+}
+
+// an element's animation, which keeps its progress for as long as the element keeps its animation-name
+#[derive(Clone, Debug)]
+pub(crate) struct Animation {
+    pub(crate) name: String,
+    pub(crate) timing: Timing,
+    pub(crate) start: f32, // Time::elapsed_seconds, before the delay
+    pub(crate) paused: Option<f32>, // how far it had got when animation-play-state became paused
+    pub(crate) settled: bool, // whether it's been drawn since it last changed, i.e. since it ended or paused
+    pub(crate) properties: Vec<(&'static str, Vec<(f32, Value)>)>,
+}
+
+impl Animation {
+    // seconds since the animation started, not counting pauses
+    pub(crate) fn elapsed(&self, now: f32) -> f32 {
+        self.paused.unwrap_or(now - self.start)
+    }
+
+    // when the last iteration ends, counting from the start
+    fn end(&self) -> f32 {
+        let Timing { duration, iterations, delay, .. } = self.timing;
+        if duration == 0. || iterations == 0. { delay } else { delay + duration * iterations }
+    }
+
+    // whether it's still changing from one frame to the next
+    fn is_running(&self, now: f32) -> bool {
+        self.paused.is_none() && self.elapsed(now) <= self.end()
+    }
+
+    // how far through the keyframes (0 to 1) the animation is, None when it doesn't apply, i.e. during the delay
+    // or after the end unless animation-fill-mode says otherwise
+    pub(crate) fn progress(&self, now: f32) -> Option<f32> {
+        let Timing { duration, iterations, delay, direction, fill_mode, .. } = self.timing;
+        let active = self.elapsed(now) - delay;
+
+        let (iteration, progress) = if active < 0. {
+            if !matches!(fill_mode, FillMode::Backwards | FillMode::Both) { return None; }
+            (0., 0.)
+        } else if active >= self.end() - delay {
+            if !matches!(fill_mode, FillMode::Forwards | FillMode::Both) { return None; }
+
+            // the end of the last iteration, which may be a partial one, e.g. animation-iteration-count: 1.5
+            match iterations {
+                _ if iterations == 0. => (0., 0.),
+                _ if iterations.is_infinite() => (0., 1.),
+                _ if iterations.fract() == 0. => (iterations - 1., 1.),
+                _ => (iterations.floor(), iterations.fract()),
+            }
+        } else {
+            let position = active / duration;
+            (position.floor(), position.fract())
+        };
+
+        let is_reversed = match direction {
+            Direction::Normal => false,
+            Direction::Reverse => true,
+            Direction::Alternate => iteration % 2. == 1.,
+            Direction::AlternateReverse => iteration % 2. == 0.,
+        };
+
+        Some(if is_reversed { 1. - progress } else { progress })
+    }
+
+    // set every animated property to its value at this point in time,
+    // easing each step from one keyframe to the next rather than the whole iteration
+    // FIXME animation-timing-function inside of a keyframe is ignored
+    pub(crate) fn apply(&self, style: &mut ComputedStyle, now: f32) {
+        let Some(progress) = self.progress(now) else { return; };
+
+        for (property, frames) in self.properties.iter() {
+            let next = frames.iter().position(|(offset, _)| *offset >= progress).unwrap_or(frames.len() - 1).max(1);
+            let ((from_offset, from), (to_offset, to)) = (&frames[next - 1], &frames[next]);
+
+            let step = if to_offset > from_offset { (progress - from_offset) / (to_offset - from_offset) } else { 1. };
+            transition::set(style, property, from.interpolate(to, self.timing.easing.ease(step)));
+        }
+    }
+}
+
+// the animations of an element, in animation-name order, added by css::restyle the first time it's styled
+#[derive(Component, Default)]
+pub(crate) struct Animations(pub(crate) Vec<Animation>);
+
+impl Animations {
+    // follow changes to animation-*: start animations whose name is new, drop the ones whose name is gone,
+    // pause and resume the rest, and recompute every keyframe against the element's new style
+    pub(crate) fn update(
+        &mut self,
+        css: &CSS,
+        computed: &ComputedStyle,
+        now: f32,
+        mut cascade: impl FnMut(&Keyframe) -> ComputedStyle,
+    ) {
+        let mut previous = std::mem::take(&mut self.0);
+
+        for (index, name) in computed.animation.name.iter().flatten().enumerate() {
+            if name == "none" { continue; }
+
+            let Some(keyframes) = css.keyframes(name) else {
+                warn!("no @keyframes named `{}`", name);
+                continue;
+            };
+
+            let timing = computed.animation.of(index);
+            let mut animation = match previous.iter().position(|animation| animation.name == *name) {
+                Some(index) => previous.remove(index),
+                None => Animation { name: name.clone(), timing, start: now, paused: None, settled: false, properties: vec![] },
+            };
+
+            match (animation.paused, timing.play_state) {
+                (None, PlayState::Paused) => animation.paused = Some(now - animation.start),
+                (Some(elapsed), PlayState::Running) => {
+                    animation.start = now - elapsed;
+                    animation.paused = None;
+                }
+                _ => {}
+            }
+
+            animation.timing = timing;
+            animation.settled = false;
+            animation.properties = keyframes.properties(css, computed, &mut cascade);
+            self.0.push(animation);
+        }
+    }
+}
+
+// Draw every element which is animated or has a running transition as the cascade had it, but with each
+// animated property somewhere between its keyframes, and each transitioning property somewhere between its
+// old and new value. ComputedStyle keeps the cascaded value throughout
+pub(crate) fn animate(
+    time: Res<Time>,
+    mut elements: Query<(Entity, Ref<ComputedStyle>, &mut Transitions, &mut Animations)>,
+    computed: Query<&ComputedStyle>,
+    children: Query<&Children>,
+    mut draw: ParamSet<(Query<Drawable>, Query<(Entity, &InlineSections, &mut Text)>)>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    let now = time.elapsed_seconds();
+    let mut animated = HashMap::new();
+    let mut is_animating = false;
+
+    for (entity, target, mut transitions, mut animations) in elements.iter_mut() {
+        // css::restyle has just drawn the cascaded style, which paused and filled animations have to be drawn over
+        let is_changing = !transitions.0.is_empty() || animations.0.iter().any(|animation| !animation.settled);
+        if !is_changing && (animations.0.is_empty() || !target.is_changed()) { continue; }
+        is_animating |= is_changing;
+
+        // animations override the cascade, and transitions override animations
+        let mut style = (*target).clone();
+        for animation in animations.0.iter_mut() {
+            animation.apply(&mut style, now);
+            animation.settled = !animation.is_running(now);
+        }
+        transitions.apply(&mut style, now);
+
+        propagate(entity, &target, &style, &computed, &children, &mut animated);
+        animated.insert(entity, style);
+    }
+
+    if animated.is_empty() { return; }
+
+    let mut drawables = draw.p0();
+    for (entity, style) in animated.iter() {
+        if let Ok(drawable) = drawables.get_mut(*entity) {
+            css::draw(style, drawable);
+        }
+    }
+
+    // text colors are in the sections of the element's Text, see text::sections
+    let style_of = |entity: Entity| animated.get(&entity).or_else(|| computed.get(entity).ok());
+
+    for (entity, sections, mut text) in draw.p1().iter_mut() {
+        if !animated.contains_key(&entity) && !sections.spans.iter().flatten().any(|span| animated.contains_key(span)) { continue; }
+
+        for (section, span) in text.sections.iter_mut().zip(sections.spans.iter()) {
+            if let Some(style) = style_of(span.unwrap_or(entity)) {
+                section.style = text::section_style(style);
+            }
+        }
+    }
+
+    // until everything is done, see desktop_app() in main
+    if is_animating { redraw.send(RequestRedraw); }
+}
+
+// Children which inherit color see the parent's animation too, and opacity fades the whole subtree.
+// FIXME other inherited properties (e.g. font-size) only animate on the element which declared them
+fn propagate(
+    entity: Entity,
+    target: &ComputedStyle,
+    style: &ComputedStyle,
+    computed: &Query<&ComputedStyle>,
+    children: &Query<&Children>,
+    animated: &mut HashMap<Entity, ComputedStyle>,
+) {
+    if style.text.color == target.text.color && style.opacity() == target.opacity() { return; }
+
+    for child in children.get(entity).into_iter().flatten() {
+        let Ok(child_target) = computed.get(*child) else { continue; };
+        let mut child_style = animated.get(child).cloned().unwrap_or_else(|| child_target.clone());
+
+        let inherits_color = child_target.sources.get("color").map_or(true, |source| source.starts_with("inherited from"));
+        if inherits_color { child_style.text.color = style.text.color; }
+
+        // the child keeps its own opacity, but fades along with the parent's current one
+        child_style.inherited_opacity = Some(style.opacity());
+
+        propagate(*child, child_target, &child_style, computed, children, animated);
+        animated.insert(*child, child_style);
+    }
+}
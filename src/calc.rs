@@ -0,0 +1,442 @@
+use std::fmt;
+
+use bevy::prelude::*;
+
+use crate::computed::ComputedStyle;
+use crate::css::CSS;
+
+// every property which accepts a <length>, see declarations::length_of
+pub(crate) const PROPERTIES: [&str; 32] = [
+    "top", "right", "bottom", "left",
+    "width", "height", "min-width", "min-height", "max-width", "max-height",
+    "margin-top", "margin-right", "margin-bottom", "margin-left",
+    "padding-top", "padding-right", "padding-bottom", "padding-left",
+    "border-top-width", "border-right-width", "border-bottom-width", "border-left-width",
+    "border-top-left-radius", "border-top-right-radius", "border-bottom-right-radius", "border-bottom-left-radius",
+    "flex-basis", "row-gap", "column-gap", "font-size",
+    "letter-spacing", "word-spacing",
+];
+
+// A CSS <length>, which (unlike Val) can be an expression like calc(100% - var(--header-height)) or
+// clamp(1rem, 2vw, 2rem), or be relative to the font size. Anything which Val can't represent is stored
+// in ComputedStyle::lengths and evaluated against the layout every frame, see evaluate()
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Length {
+    Auto,
+    Number(f32), // unitless, only for * and /
+    Px(f32),
+    Percent(f32),
+    Em(f32),
+    Rem(f32),
+    Vw(f32),
+    Vh(f32),
+    VMin(f32),
+    VMax(f32),
+    Sum(Box<Length>, Box<Length>),
+    Difference(Box<Length>, Box<Length>),
+    Product(Box<Length>, Box<Length>),
+    Quotient(Box<Length>, Box<Length>),
+    Min(Vec<Length>),
+    Max(Vec<Length>),
+    Clamp(Box<Length>, Box<Length>, Box<Length>),
+}
+
+// what relative lengths are relative to
+pub(crate) struct Context {
+    pub(crate) percent_of: f32,
+    pub(crate) font_size: f32,
+    pub(crate) rem: f32,
+    pub(crate) viewport: Vec2,
+}
+
+impl Length {
+    pub(crate) fn parse(value: &str) -> Result<Length, String> {
+        match value.trim() {
+            "auto" => return Ok(Length::Auto),
+            // border widths
+            "thin" => return Ok(Length::Px(1.)),
+            "medium" => return Ok(Length::Px(3.)),
+            "thick" => return Ok(Length::Px(5.)),
+            _ => {}
+        }
+
+        let mut parser = Parser { tokens: tokenize(value)?, position: 0 };
+        let length = parser.sum()?;
+
+        if parser.position < parser.tokens.len() {
+            return Err(format!("unexpected `{:?}` in `{}`", parser.tokens[parser.position], value));
+        }
+
+        match length {
+            // unitless zero is the only number which is also a length
+            Length::Number(number) if number != 0. => Err(format!("`{}` needs a unit", value)),
+            length => Ok(length),
+        }
+    }
+
+    // Some if Val can represent this length as-is
+    pub(crate) fn to_val(&self, rem: f32) -> Option<Val> {
+        match self {
+            Length::Auto => Some(Val::Auto),
+            Length::Number(number) if *number == 0. => Some(Val::ZERO),
+            Length::Px(px) => Some(Val::Px(*px)),
+            Length::Percent(percent) => Some(Val::Percent(*percent)),
+            // FIXME -- rem is a constant for now, see css::recalculate
+            Length::Rem(rem_count) => Some(Val::Px(rem_count * rem)),
+            Length::Vw(vw) => Some(Val::Vw(*vw)),
+            Length::Vh(vh) => Some(Val::Vh(*vh)),
+            Length::VMin(vmin) => Some(Val::VMin(*vmin)),
+            Length::VMax(vmax) => Some(Val::VMax(*vmax)),
+            _ => None
+        }
+    }
+
+    // in px
+    pub(crate) fn evaluate(&self, context: &Context) -> f32 {
+        match self {
+            Length::Auto => 0.,
+            Length::Number(number) => *number,
+            Length::Px(px) => *px,
+            Length::Percent(percent) => percent / 100. * context.percent_of,
+            Length::Em(em) => em * context.font_size,
+            Length::Rem(rem) => rem * context.rem,
+            Length::Vw(vw) => vw / 100. * context.viewport.x,
+            Length::Vh(vh) => vh / 100. * context.viewport.y,
+            Length::VMin(vmin) => vmin / 100. * context.viewport.min_element(),
+            Length::VMax(vmax) => vmax / 100. * context.viewport.max_element(),
+            Length::Sum(a, b) => a.evaluate(context) + b.evaluate(context),
+            Length::Difference(a, b) => a.evaluate(context) - b.evaluate(context),
+            Length::Product(a, b) => a.evaluate(context) * b.evaluate(context),
+            Length::Quotient(a, b) => a.evaluate(context) / b.evaluate(context),
+            Length::Min(each) => each.iter().map(|length| length.evaluate(context)).fold(f32::INFINITY, f32::min),
+            Length::Max(each) => each.iter().map(|length| length.evaluate(context)).fold(f32::NEG_INFINITY, f32::max),
+            // clamp(min, value, max) == max(min, min(value, max))
+            Length::Clamp(min, value, max) => value.evaluate(context).min(max.evaluate(context)).max(min.evaluate(context)),
+        }
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |each: &Vec<Length>| each.iter().map(|length| length.to_string()).collect::<Vec<String>>().join(", ");
+
+        match self {
+            Length::Auto => write!(f, "auto"),
+            Length::Number(number) => write!(f, "{}", number),
+            Length::Px(px) => write!(f, "{}px", px),
+            Length::Percent(percent) => write!(f, "{}%", percent),
+            Length::Em(em) => write!(f, "{}em", em),
+            Length::Rem(rem) => write!(f, "{}rem", rem),
+            Length::Vw(vw) => write!(f, "{}vw", vw),
+            Length::Vh(vh) => write!(f, "{}vh", vh),
+            Length::VMin(vmin) => write!(f, "{}vmin", vmin),
+            Length::VMax(vmax) => write!(f, "{}vmax", vmax),
+            Length::Sum(a, b) => write!(f, "({} + {})", a, b),
+            Length::Difference(a, b) => write!(f, "({} - {})", a, b),
+            Length::Product(a, b) => write!(f, "({} * {})", a, b),
+            Length::Quotient(a, b) => write!(f, "({} / {})", a, b),
+            Length::Min(each) => write!(f, "min({})", list(each)),
+            Length::Max(each) => write!(f, "max({})", list(each)),
+            Length::Clamp(min, value, max) => write!(f, "clamp({}, {}, {})", min, value, max),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Dimension(f32, String), // number and unit, e.g. 100%, 2.5rem or 3
+    Operator(char),
+    Function(String), // includes the opening parenthesis, e.g. calc(
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(value: &str) -> Result<Vec<Token>, String> {
+    let chars = value.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut index = 0;
+
+    while index < chars.len() {
+        let each = chars[index];
+        let next_is_digit = chars.get(index + 1).is_some_and(|next| next.is_ascii_digit() || *next == '.');
+        let follows_operand = matches!(tokens.last(), Some(Token::Dimension(..)) | Some(Token::Close));
+
+        if each.is_whitespace() {
+            index += 1;
+        } else if each.is_ascii_digit() || each == '.' || ((each == '-' || each == '+') && next_is_digit && !follows_operand) {
+            let start = index;
+            index += 1;
+            while index < chars.len() && (chars[index].is_ascii_digit() || chars[index] == '.') { index += 1; }
+            let number = chars[start..index].iter().collect::<String>();
+            let number = number.parse::<f32>().map_err(|_| format!("`{}` is not a number", number))?;
+
+            let start = index;
+            while index < chars.len() && (chars[index].is_ascii_alphabetic() || chars[index] == '%') { index += 1; }
+            tokens.push(Token::Dimension(number, chars[start..index].iter().collect::<String>().to_lowercase()));
+        } else if each.is_ascii_alphabetic() {
+            let start = index;
+            while index < chars.len() && (chars[index].is_ascii_alphabetic() || chars[index] == '-') { index += 1; }
+            let name = chars[start..index].iter().collect::<String>().to_lowercase();
+
+            if chars.get(index) != Some(&'(') { return Err(format!("unexpected `{}`", name)); }
+            index += 1;
+            tokens.push(Token::Function(name));
+        } else {
+            tokens.push(match each {
+                '(' => Token::Open,
+                ')' => Token::Close,
+                ',' => Token::Comma,
+                '+' | '-' | '*' | '/' => Token::Operator(each),
+                _ => return Err(format!("unexpected `{}`", each)),
+            });
+            index += 1;
+        }
+    }
+
+    Ok(tokens)
+}
+
+// recursive descent, lowest precedence first
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            other => Err(format!("expected {:?}, found {:?}", expected, other)),
+        }
+    }
+
+    // a + b, a - b
+    fn sum(&mut self) -> Result<Length, String> {
+        let mut length = self.product()?;
+        while let Some(Token::Operator(operator @ ('+' | '-'))) = self.peek().cloned() {
+            self.position += 1;
+            let rhs = Box::new(self.product()?);
+            length = if operator == '+' { Length::Sum(Box::new(length), rhs) } else { Length::Difference(Box::new(length), rhs) };
+        }
+        Ok(length)
+    }
+
+    // a * b, a / b
+    fn product(&mut self) -> Result<Length, String> {
+        let mut length = self.value()?;
+        while let Some(Token::Operator(operator @ ('*' | '/'))) = self.peek().cloned() {
+            self.position += 1;
+            let rhs = Box::new(self.value()?);
+            length = if operator == '*' { Length::Product(Box::new(length), rhs) } else { Length::Quotient(Box::new(length), rhs) };
+        }
+        Ok(length)
+    }
+
+    fn value(&mut self) -> Result<Length, String> {
+        match self.next() {
+            Some(Token::Dimension(number, unit)) => match unit.as_str() {
+                "" => Ok(Length::Number(number)),
+                "px" => Ok(Length::Px(number)),
+                "%" => Ok(Length::Percent(number)),
+                "em" => Ok(Length::Em(number)),
+                "rem" => Ok(Length::Rem(number)),
+                "vw" => Ok(Length::Vw(number)),
+                "vh" => Ok(Length::Vh(number)),
+                "vmin" => Ok(Length::VMin(number)),
+                "vmax" => Ok(Length::VMax(number)),
+                other => Err(format!("unsupported unit `{}`", other)),
+            },
+            Some(Token::Open) => {
+                let length = self.sum()?;
+                self.expect(Token::Close)?;
+                Ok(length)
+            }
+            Some(Token::Operator('-')) => Ok(Length::Product(Box::new(Length::Number(-1.)), Box::new(self.value()?))),
+            Some(Token::Function(name)) => {
+                let mut arguments = vec![self.sum()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.position += 1;
+                    arguments.push(self.sum()?);
+                }
+                self.expect(Token::Close)?;
+
+                match (name.as_str(), arguments.len()) {
+                    ("calc", 1) => Ok(arguments.remove(0)),
+                    ("min", _) => Ok(Length::Min(arguments)),
+                    ("max", _) => Ok(Length::Max(arguments)),
+                    ("clamp", 3) => {
+                        let [min, value, max] = <[Length; 3]>::try_from(arguments).map_err(|_| "clamp() takes 3 arguments")?;
+                        Ok(Length::Clamp(Box::new(min), Box::new(value), Box::new(max)))
+                    }
+                    (name, count) => Err(format!("{}() does not take {} arguments", name, count)),
+                }
+            }
+            other => Err(format!("unexpected {:?}", other)),
+        }
+    }
+}
+
+// the field of Style which a length property is evaluated into
+pub(crate) fn field<'a>(style: &'a mut Style, property: &str) -> Option<&'a mut Val> {
+    Some(match property {
+        "top" => &mut style.top,
+        "right" => &mut style.right,
+        "bottom" => &mut style.bottom,
+        "left" => &mut style.left,
+        "width" => &mut style.width,
+        "height" => &mut style.height,
+        "min-width" => &mut style.min_width,
+        "min-height" => &mut style.min_height,
+        "max-width" => &mut style.max_width,
+        "max-height" => &mut style.max_height,
+        "margin-top" => &mut style.margin.top,
+        "margin-right" => &mut style.margin.right,
+        "margin-bottom" => &mut style.margin.bottom,
+        "margin-left" => &mut style.margin.left,
+        "padding-top" => &mut style.padding.top,
+        "padding-right" => &mut style.padding.right,
+        "padding-bottom" => &mut style.padding.bottom,
+        "padding-left" => &mut style.padding.left,
+        "border-top-width" => &mut style.border.top,
+        "border-right-width" => &mut style.border.right,
+        "border-bottom-width" => &mut style.border.bottom,
+        "border-left-width" => &mut style.border.left,
+        "flex-basis" => &mut style.flex_basis,
+        "row-gap" => &mut style.row_gap,
+        "column-gap" => &mut style.column_gap,
+        _ => return None,
+    })
+}
+
+// evaluate every calc(), em, etc. against the layout of the previous frame, before Bevy lays out the UI
+pub(crate) fn evaluate(
+    css: Res<CSS>,
+    window: Query<&Window>,
+    mut elements: Query<(&ComputedStyle, &mut Style, Option<&mut Text>, Option<&mut BorderRadius>, Option<&Node>, Option<&Parent>)>,
+    parents: Query<(&Node, &ComputedStyle)>,
+) {
+    let window = window.single();
+    let viewport = Vec2::new(window.width(), window.height());
+
+    for (computed, mut style, text, border_radius, node, parent) in elements.iter_mut() {
+        if computed.lengths.is_empty() { continue; }
+
+        // FIXME percentages should be relative to the content box of the parent, not the border box
+        let (parent_size, parent_font_size) = parent
+            .and_then(|parent| parents.get(parent.get()).ok())
+            .map(|(node, parent_computed)| (node.size(), parent_computed.text.font_size))
+            .unwrap_or((viewport, TextStyle::default().font_size));
+
+        let context = |percent_of: f32, font_size: f32| Context { percent_of, font_size, rem: css.rem, viewport };
+
+        // font-size is relative to the parent's font, everything else is relative to the element's own font
+        let font_size = match computed.lengths.get("font-size") {
+            Some(length) => length.evaluate(&context(parent_font_size, parent_font_size)),
+            None => computed.text.font_size,
+        };
+
+        let mut evaluated = style.clone();
+        let mut radius = computed.border_radius;
+        for (property, length) in computed.lengths.iter() {
+            let is_vertical = matches!(*property, "height" | "min-height" | "max-height" | "top" | "bottom" | "row-gap");
+            let is_corner = property.ends_with("-radius");
+            let percent_of = if is_corner {
+                // like Bevy, relative to the smaller side of the element itself
+                node.map_or(0., |node| node.size().min_element())
+            } else if is_vertical {
+                parent_size.y
+            } else {
+                parent_size.x
+            };
+            let px = Val::Px(length.evaluate(&context(percent_of, font_size)));
+
+            match *property {
+                "border-top-left-radius" => radius.top_left = px,
+                "border-top-right-radius" => radius.top_right = px,
+                "border-bottom-right-radius" => radius.bottom_right = px,
+                "border-bottom-left-radius" => radius.bottom_left = px,
+                _ => if let Some(field) = field(&mut evaluated, property) { *field = px; },
+            }
+        }
+
+        // only write when something changed, so that Bevy doesn't redo the layout every frame
+        if *style != evaluated { *style = evaluated; }
+
+        if let Some(mut border_radius) = border_radius {
+            if *border_radius != radius { *border_radius = radius; }
+        }
+
+        if let Some(mut text) = text {
+            if computed.lengths.contains_key("font-size") && text.sections.iter().any(|section| section.style.font_size != font_size) {
+                for section in text.sections.iter_mut() {
+                    section.style.font_size = font_size;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1rem is 16px, on a 1000x800 viewport
+    fn evaluate(value: &str, percent_of: f32) -> f32 {
+        let context = Context { percent_of, font_size: 20., rem: 16., viewport: Vec2::new(1000., 800.) };
+        Length::parse(value).unwrap_or_else(|error| panic!("`{}`: {}", value, error)).evaluate(&context)
+    }
+
+    #[test]
+    fn units() {
+        assert_eq!(Length::parse("10px"), Ok(Length::Px(10.)));
+        assert_eq!(Length::parse("-1.5REM"), Ok(Length::Rem(-1.5)));
+        assert_eq!(Length::parse("0"), Ok(Length::Number(0.)));
+        assert_eq!(Length::parse("auto"), Ok(Length::Auto));
+        assert_eq!(evaluate("2em", 0.), 40.);
+        assert_eq!(evaluate("10vh", 0.), 80.);
+        assert_eq!(evaluate("50%", 300.), 150.);
+    }
+
+    #[test]
+    fn calc_follows_precedence() {
+        assert_eq!(evaluate("calc(100% - 2 * 10px)", 200.), 180.);
+        assert_eq!(evaluate("calc((100% - 2rem) / 2)", 200.), 84.);
+        assert_eq!(evaluate("calc(1px - -1px)", 0.), 2.);
+        assert_eq!(evaluate("-calc(1rem + 4px)", 0.), -20.);
+    }
+
+    #[test]
+    fn min_max_and_clamp() {
+        assert_eq!(evaluate("min(10px, 2vw, 5em)", 0.), 10.);
+        assert_eq!(evaluate("max(10px, 2vw)", 0.), 20.);
+        assert_eq!(evaluate("clamp(1rem, 2vw, 2rem)", 0.), 20.);
+        assert_eq!(evaluate("clamp(1rem, 1vw, 2rem)", 0.), 16.);
+        assert_eq!(evaluate("clamp(1rem, 5vw, 2rem)", 0.), 32.);
+    }
+
+    #[test]
+    fn only_plain_lengths_are_vals() {
+        assert_eq!(Length::parse("2rem").unwrap().to_val(16.), Some(Val::Px(32.)));
+        assert_eq!(Length::parse("50%").unwrap().to_val(16.), Some(Val::Percent(50.)));
+        assert_eq!(Length::parse("calc(1px + 1px)").unwrap().to_val(16.), None);
+        assert_eq!(Length::parse("1em").unwrap().to_val(16.), None);
+    }
+
+    #[test]
+    fn rejects_invalid_lengths() {
+        for value in ["", "10", "10furlongs", "1px 2px", "calc(1px +)", "calc(1px", "clamp(1px, 2px)", "foo(1px)", "1px)"] {
+            assert!(Length::parse(value).is_err(), "expected `{}` to be rejected, found {:?}", value, Length::parse(value));
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+
+// a parsed CSS <color>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CssColor {
+    Color(Color),
+    // resolved against the element's `color` property by whoever uses it
+    CurrentColor,
+}
+
+// parse any CSS color syntax: #rgb, #rgba, #rrggbb, #rrggbbaa, rgb(), rgba(), hsl(), hsla(), named colors,
+// transparent and currentColor
+pub(crate) fn parse(value: &str) -> Result<CssColor, String> {
+    let value = value.trim();
+    let lowercase = value.to_lowercase();
+
+    if let Some(hex) = value.strip_prefix('#') {
+        return hex_color(hex).map(CssColor::Color);
+    }
+
+    match lowercase.as_str() {
+        "currentcolor" => return Ok(CssColor::CurrentColor),
+        "transparent" => return Ok(CssColor::Color(Color::NONE)),
+        _ => {}
+    }
+
+    if let Some((function, arguments)) = lowercase.strip_suffix(')').and_then(|each| each.split_once('(')) {
+        let arguments = arguments_of(arguments);
+        return match function.trim() {
+            "rgb" | "rgba" => rgb(&arguments).map(CssColor::Color),
+            "hsl" | "hsla" => hsl(&arguments).map(CssColor::Color),
+            other => Err(format!("unknown color function `{}()`", other)),
+        };
+    }
+
+    NAMED_COLORS.iter()
+        .find(|(name, _)| *name == lowercase)
+        .map(|(_, rgb)| CssColor::Color(Srgba::rgb_u8((rgb >> 16) as u8, (rgb >> 8) as u8, *rgb as u8).into()))
+        .ok_or_else(|| format!("`{}` is not a color", value))
+}
+
+fn hex_color(hex: &str) -> Result<Color, String> {
+    if !matches!(hex.len(), 3 | 4 | 6 | 8) || !hex.chars().all(|each| each.is_ascii_hexdigit()) {
+        return Err(format!("`#{}` should be #rgb, #rgba, #rrggbb or #rrggbbaa", hex));
+    }
+    Srgba::hex(hex).map(Color::from).map_err(|error| format!("`#{}`: {}", hex, error))
+}
+
+// "10, 20, 30" or "10 20 30 / 50%" => ["10", "20", "30", "50%"]
+fn arguments_of(arguments: &str) -> Vec<&str> {
+    let (channels, alpha) = match arguments.split_once('/') {
+        Some((channels, alpha)) => (channels, Some(alpha.trim())),
+        None => (arguments, None),
+    };
+
+    channels.split(|each: char| each == ',' || each.is_whitespace())
+        .filter(|each| !each.is_empty())
+        .chain(alpha)
+        .collect()
+}
+
+fn rgb(arguments: &[&str]) -> Result<Color, String> {
+    let [red, green, blue, alpha @ ..] = arguments else {
+        return Err(format!("rgb() expects 3 or 4 arguments, found {}", arguments.len()));
+    };
+
+    let channel = |value: &str| match value.strip_suffix('%') {
+        Some(percent) => number(percent).map(|percent| percent / 100.),
+        None => number(value).map(|value| value / 255.),
+    };
+
+    Ok(Color::srgba(channel(*red)?, channel(*green)?, channel(*blue)?, alpha_of(alpha)?))
+}
+
+fn hsl(arguments: &[&str]) -> Result<Color, String> {
+    let [hue, saturation, lightness, alpha @ ..] = arguments else {
+        return Err(format!("hsl() expects 3 or 4 arguments, found {}", arguments.len()));
+    };
+
+    let hue = if let Some(turns) = hue.strip_suffix("turn") {
+        number(turns)? * 360.
+    } else if let Some(radians) = hue.strip_suffix("rad") {
+        number(radians)?.to_degrees()
+    } else {
+        number(hue.strip_suffix("deg").unwrap_or(*hue))?
+    };
+
+    let percentage = |value: &str| value.strip_suffix('%')
+        .ok_or_else(|| format!("expected a percentage, found `{}`", value))
+        .and_then(number)
+        .map(|percent| percent / 100.);
+
+    Ok(Color::hsla(hue.rem_euclid(360.), percentage(*saturation)?, percentage(*lightness)?, alpha_of(alpha)?))
+}
+
+// optional alpha channel, either 0..1 or 0%..100%
+fn alpha_of(alpha: &[&str]) -> Result<f32, String> {
+    match alpha {
+        [] => Ok(1.),
+        [alpha] => match alpha.strip_suffix('%') {
+            Some(percent) => number(percent).map(|percent| percent / 100.),
+            None => number(alpha),
+        },
+        _ => Err("too many arguments".into()),
+    }
+}
+
+fn number(value: &str) -> Result<f32, String> {
+    value.trim().parse::<f32>().map_err(|_| format!("expected a number, found `{}`", value))
+}
+
+// https://www.w3.org/TR/css-color-4/#named-colors
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff), ("antiquewhite", 0xfaebd7), ("aqua", 0x00ffff), ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff), ("beige", 0xf5f5dc), ("bisque", 0xffe4c4), ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd), ("blue", 0x0000ff), ("blueviolet", 0x8a2be2), ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887), ("cadetblue", 0x5f9ea0), ("chartreuse", 0x7fff00), ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50), ("cornflowerblue", 0x6495ed), ("cornsilk", 0xfff8dc), ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff), ("darkblue", 0x00008b), ("darkcyan", 0x008b8b), ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9), ("darkgreen", 0x006400), ("darkgrey", 0xa9a9a9), ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b), ("darkolivegreen", 0x556b2f), ("darkorange", 0xff8c00), ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000), ("darksalmon", 0xe9967a), ("darkseagreen", 0x8fbc8f), ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f), ("darkslategrey", 0x2f4f4f), ("darkturquoise", 0x00ced1), ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493), ("deepskyblue", 0x00bfff), ("dimgray", 0x696969), ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff), ("firebrick", 0xb22222), ("floralwhite", 0xfffaf0), ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff), ("gainsboro", 0xdcdcdc), ("ghostwhite", 0xf8f8ff), ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520), ("gray", 0x808080), ("green", 0x008000), ("greenyellow", 0xadff2f),
+    ("grey", 0x808080), ("honeydew", 0xf0fff0), ("hotpink", 0xff69b4), ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082), ("ivory", 0xfffff0), ("khaki", 0xf0e68c), ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5), ("lawngreen", 0x7cfc00), ("lemonchiffon", 0xfffacd), ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080), ("lightcyan", 0xe0ffff), ("lightgoldenrodyellow", 0xfafad2), ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90), ("lightgrey", 0xd3d3d3), ("lightpink", 0xffb6c1), ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa), ("lightskyblue", 0x87cefa), ("lightslategray", 0x778899), ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de), ("lightyellow", 0xffffe0), ("lime", 0x00ff00), ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6), ("magenta", 0xff00ff), ("maroon", 0x800000), ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd), ("mediumorchid", 0xba55d3), ("mediumpurple", 0x9370db), ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee), ("mediumspringgreen", 0x00fa9a), ("mediumturquoise", 0x48d1cc), ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970), ("mintcream", 0xf5fffa), ("mistyrose", 0xffe4e1), ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead), ("navy", 0x000080), ("oldlace", 0xfdf5e6), ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23), ("orange", 0xffa500), ("orangered", 0xff4500), ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa), ("palegreen", 0x98fb98), ("paleturquoise", 0xafeeee), ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5), ("peachpuff", 0xffdab9), ("peru", 0xcd853f), ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd), ("powderblue", 0xb0e0e6), ("purple", 0x800080), ("rebeccapurple", 0x663399),
+    ("red", 0xff0000), ("rosybrown", 0xbc8f8f), ("royalblue", 0x4169e1), ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072), ("sandybrown", 0xf4a460), ("seagreen", 0x2e8b57), ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d), ("silver", 0xc0c0c0), ("skyblue", 0x87ceeb), ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090), ("slategrey", 0x708090), ("snow", 0xfffafa), ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4), ("tan", 0xd2b48c), ("teal", 0x008080), ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347), ("turquoise", 0x40e0d0), ("violet", 0xee82ee), ("wheat", 0xf5deb3),
+    ("white", 0xffffff), ("whitesmoke", 0xf5f5f5), ("yellow", 0xffff00), ("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // as #RRGGBB or #RRGGBBAA, so that colors parsed from different syntaxes compare exactly
+    fn hex(value: &str) -> String {
+        match parse(value) {
+            Ok(CssColor::Color(color)) => color.to_srgba().to_hex(),
+            other => panic!("expected `{}` to be a color, found {:?}", value, other),
+        }
+    }
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(hex("#fff"), "#FFFFFF");
+        assert_eq!(hex("#f008"), "#FF000088");
+        assert_eq!(hex("#6a8fb3"), "#6A8FB3");
+        assert_eq!(hex(" #6A8FB380 "), "#6A8FB380");
+    }
+
+    #[test]
+    fn rgb_and_hsl() {
+        assert_eq!(hex("rgb(255, 0, 0)"), "#FF0000");
+        assert_eq!(hex("rgba(0, 0, 255, 0.5)"), "#0000FF80");
+        assert_eq!(hex("rgb(0 128 0 / 50%)"), "#00800080");
+        assert_eq!(hex("RGB(100%, 0%, 0%)"), "#FF0000");
+        assert_eq!(hex("hsl(120, 100%, 50%)"), "#00FF00");
+        assert_eq!(hex("hsla(0.5turn, 100%, 50%, 1)"), "#00FFFF");
+        // hues wrap around
+        assert_eq!(hex("hsl(-120deg 100% 50%)"), "#0000FF");
+    }
+
+    #[test]
+    fn named_colors() {
+        assert_eq!(hex("rebeccapurple"), "#663399");
+        assert_eq!(hex("Red"), "#FF0000");
+        assert_eq!(hex("transparent"), "#00000000");
+        assert_eq!(parse("currentColor"), Ok(CssColor::CurrentColor));
+    }
+
+    #[test]
+    fn rejects_invalid_colors() {
+        for value in ["", "#12345", "#ggg", "rgb(1, 2)", "rgb(1, 2, 3, 4, 5)", "rgb(red, 0, 0)", "hsl(120, 100, 50)", "lab(50% 0 0)", "blurple"] {
+            assert!(parse(value).is_err(), "expected `{}` to be rejected, found {:?}", value, parse(value));
+        }
+    }
+}
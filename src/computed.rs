@@ -0,0 +1,367 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+use crate::animation::AnimationProperties;
+use crate::calc::Length;
+use crate::css::{Classes, CSS, Styles};
+use crate::scroll::ScrollProperties;
+use crate::effects::Effects;
+use crate::fonts::FontProperties;
+use crate::text::TextProperties;
+use crate::transform::TransformProperties;
+use crate::transition::TransitionProperties;
+use crate::patch::Patch;
+
+// properties which children take from their parent when no rule sets them
+const INHERITED: [&str; 14] = [
+    "color", "font-family", "font-weight", "font-style", "font-size", "letter-spacing", "word-spacing", "white-space", "line-height",
+    "text-transform", "text-align", "text-decoration-line", "text-decoration-color", "text-decoration-thickness",
+];
+
+// The final value of every property after the cascade, like the "Computed" panel in browser devtools.
+// Style, BackgroundColor, BorderColor, Outline, UiImage and Text are all written from this by css::restyle.
+#[derive(Component, Clone, Default)]
+pub(crate) struct ComputedStyle {
+    pub(crate) style: Style,
+    pub(crate) background_color: Color,
+    pub(crate) border_color: Color,
+    pub(crate) border_radius: BorderRadius,
+    pub(crate) outline: Outline,
+    pub(crate) text: TextStyle,
+    // resolved into text.font by css.fonts
+    pub(crate) font: FontProperties,
+    pub(crate) text_properties: TextProperties,
+    // FIXME inherited as declared, so em is relative to the child's font-size rather than the parent's
+    pub(crate) letter_spacing: Option<Length>,
+    pub(crate) word_spacing: Option<Length>,
+    // Bevy can't draw a node and its children as a group, so both are combined with the parent's here:
+    // opacity is multiplied with it, and filter is followed by it, see ComputedStyle::opacity and svg::rasterize
+    pub(crate) effects: Effects,
+    // the parent's opacity, kept apart from the element's own so that animation::propagate can fade it
+    pub(crate) inherited_opacity: Option<f32>,
+    pub(crate) transition: TransitionProperties,
+    pub(crate) animation: AnimationProperties,
+    pub(crate) transform: TransformProperties,
+    pub(crate) scroll: ScrollProperties,
+    // lengths which Val can't represent, like calc() or em, evaluated every frame by calc::evaluate
+    pub(crate) lengths: BTreeMap<&'static str, Length>,
+    // property => selector of the rule which set it, e.g. "align-items" => ".header__logo"
+    pub(crate) sources: BTreeMap<&'static str, String>,
+}
+
+impl ComputedStyle {
+    pub(crate) fn cascade(
+        css: &CSS,
+        rules: Vec<(String, &Styles)>, // see CSS::rules
+        inherited: &ComputedStyle,
+        is_button: bool,
+    ) -> Self {
+        // later rules overwrite the sources of earlier ones, just like they overwrite the values
+        let mut sources = BTreeMap::new();
+        for (selector, styles) in rules.iter() {
+            for property in styles.declared() {
+                sources.insert(property, selector.clone());
+            }
+        }
+
+        for property in INHERITED {
+            if sources.contains_key(property) { continue; }
+            if let Some(source) = inherited.sources.get(property) {
+                let source = if source.starts_with("inherited from") { source.clone() } else { format!("inherited from {}", source) };
+                sources.insert(property, source);
+            }
+        }
+
+        let styles = rules.into_iter().fold(Styles::initial(), |acc, (_, patch)| acc.apply(patch));
+        let text = styles.text.text.sections.first().map(|section| section.style.clone()).unwrap_or_default();
+        let font = inherited.font.apply(&styles.font);
+        let current_color = styles.current_color.clone();
+        let is_current_color = |property: &str| current_color.get(property).copied().unwrap_or_default();
+
+        // color: currentColor is the inherited color
+        let mut text = TextStyle { font: css.fonts.resolve(&font), ..inherited.text.apply(&text) };
+        if is_current_color("color") { text.color = inherited.text.color; }
+        let color = text.color;
+
+        let mut computed = Self {
+            style: styles.style,
+            background_color: if is_button { styles.button.image.color } else { styles.node.background_color.0 },
+            border_color: if is_button { styles.button.border_color.0 } else { styles.node.border_color.0 },
+            border_radius: if is_button { styles.button.border_radius } else { styles.node.border_radius },
+            outline: styles.outline,
+            text,
+            font,
+            // text-overflow is the only one which isn't inherited
+            text_properties: TextProperties {
+                text_overflow: styles.text_properties.text_overflow,
+                ..inherited.text_properties.apply(&styles.text_properties)
+            },
+            letter_spacing: styles.lengths.get("letter-spacing").cloned().or_else(|| inherited.letter_spacing.clone()),
+            word_spacing: styles.lengths.get("word-spacing").cloned().or_else(|| inherited.word_spacing.clone()),
+            effects: Effects {
+                opacity: styles.effects.opacity,
+                filter: Some(styles.effects.filter.iter().flatten().chain(inherited.effects.filter.iter().flatten()).copied().collect()),
+            },
+            inherited_opacity: Some(inherited.opacity()),
+            transition: styles.transition,
+            animation: styles.animation,
+            transform: styles.transform,
+            scroll: styles.scroll,
+            lengths: styles.lengths.into_iter()
+                .filter(|(property, length)| !property.ends_with("-spacing") && length.to_val(css.rem).is_none())
+                .collect(),
+            sources,
+        };
+
+        // every other currentColor is this element's color
+        if is_current_color("background-color") { computed.background_color = color; }
+        if is_current_color("border-color") { computed.border_color = color; }
+        if is_current_color("outline-color") { computed.outline.color = color; }
+        // None, rather than the color, so that children use their own color, like the keyword
+        if is_current_color("text-decoration-color") { computed.text_properties.decoration_color = None; }
+        if let Some((thumb, track)) = computed.scroll.scrollbar_color.as_mut() {
+            if is_current_color("scrollbar-color-thumb") { *thumb = color; }
+            if is_current_color("scrollbar-color-track") { *track = color; }
+        }
+
+        computed
+    }
+
+    // including the opacity of every ancestor
+    pub(crate) fn opacity(&self) -> f32 {
+        self.inherited_opacity.unwrap_or(1.) * self.effects.opacity.unwrap_or(1.)
+    }
+
+    // a color which this element draws with, faded by its opacity
+    pub(crate) fn with_opacity(&self, color: Color) -> Color {
+        color.with_alpha(color.alpha() * self.opacity())
+    }
+
+    // every property which was set by some rule, with its value and source
+    pub(crate) fn dump(&self) -> String {
+        let mut properties = style_properties(&self.style).into_iter()
+            .map(|(property, value, _)| (property, value))
+            .collect::<Vec<(&'static str, String)>>();
+
+        properties.push(("background-color", format!("{:?}", self.background_color)));
+        properties.push(("border-color", format!("{:?}", self.border_color)));
+        properties.push(("border-radius", format!("{:?}", self.border_radius)));
+        properties.push(("outline", format!("{:?}", self.outline)));
+        properties.push(("opacity", format!("{} (including ancestors)", self.opacity())));
+        properties.push(("filter", format!("{:?} (including ancestors)", self.effects.filter.as_deref().unwrap_or_default())));
+        // rather than what Bevy lays it out as
+        if let Some(position) = self.scroll.position {
+            properties.retain(|(property, _)| *property != "position");
+            properties.push(("position", format!("{:?}", position)));
+        }
+        properties.push(("overflow-x", format!("{:?}", self.scroll.overflow_x.unwrap_or_default())));
+        properties.push(("overflow-y", format!("{:?}", self.scroll.overflow_y.unwrap_or_default())));
+        properties.push(("scroll-behavior", format!("{:?}", self.scroll.behavior.unwrap_or_default())));
+        properties.push(("scroll-margin-top", format!("{:?}", self.scroll.margin_top.unwrap_or(Val::ZERO))));
+        properties.push(("scrollbar-color", format!("{:?}", self.scroll.scrollbar_color)));
+        properties.push(("scrollbar-width", format!("{:?}", self.scroll.scrollbar_width.unwrap_or_default())));
+        properties.push(("transform", format!("{:?}", self.transform.transform.as_deref().unwrap_or_default())));
+        properties.push(("transform-origin", format!("{:?}", self.transform.origin)));
+        properties.push(("transition-property", format!("{:?}", self.transition.property)));
+        properties.push(("transition-duration", format!("{:?}", self.transition.duration)));
+        properties.push(("transition-timing-function", format!("{:?}", self.transition.timing_function)));
+        properties.push(("transition-delay", format!("{:?}", self.transition.delay)));
+        properties.push(("animation-name", format!("{:?}", self.animation.name)));
+        properties.push(("animation-duration", format!("{:?}", self.animation.duration)));
+        properties.push(("animation-timing-function", format!("{:?}", self.animation.timing_function)));
+        properties.push(("animation-delay", format!("{:?}", self.animation.delay)));
+        properties.push(("animation-iteration-count", format!("{:?}", self.animation.iteration_count)));
+        properties.push(("animation-direction", format!("{:?}", self.animation.direction)));
+        properties.push(("animation-fill-mode", format!("{:?}", self.animation.fill_mode)));
+        properties.push(("animation-play-state", format!("{:?}", self.animation.play_state)));
+        properties.push(("color", format!("{:?}", self.text.color)));
+        properties.push(("font-family", format!("{} ({:?})", self.font.family.as_deref().unwrap_or_default(), self.text.font)));
+        properties.push(("font-weight", format!("{}", self.font.weight.unwrap_or(400))));
+        properties.push(("font-style", format!("{:?}", self.font.style.unwrap_or_default())));
+        properties.push(("font-size", format!("{:?}", self.text.font_size)));
+        properties.push(("letter-spacing", self.letter_spacing.as_ref().map_or("normal".into(), |length| length.to_string())));
+        properties.push(("word-spacing", self.word_spacing.as_ref().map_or("normal".into(), |length| length.to_string())));
+        properties.push(("white-space", format!("{:?}", self.text_properties.white_space.unwrap_or_default())));
+        properties.push(("line-height", format!("{:?}", self.text_properties.line_height)));
+        properties.push(("text-overflow", format!("{:?}", self.text_properties.text_overflow.unwrap_or_default())));
+        properties.push(("text-transform", format!("{:?}", self.text_properties.text_transform.unwrap_or_default())));
+        properties.push(("text-align", format!("{:?}", self.text_properties.text_align.unwrap_or_default())));
+        properties.push(("text-decoration-line", format!("{:?}", self.text_properties.decoration_line.unwrap_or_default())));
+        properties.push(("text-decoration-color", self.text_properties.decoration_color.map_or("currentColor".into(), |color| format!("{:?}", color))));
+        properties.push(("text-decoration-thickness", self.text_properties.decoration_thickness.as_ref().map_or("auto".into(), |length| length.to_string())));
+
+        // show the expression rather than the placeholder it was declared as
+        for (property, length) in self.lengths.iter() {
+            properties.retain(|(each, _)| each != property);
+            properties.push((property, length.to_string()));
+        }
+
+        properties.into_iter()
+            .filter_map(|(property, value)| {
+                let source = self.sources.get(property)?;
+                Some(format!("  {}: {} /* {} */", property, value, source))
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl Styles {
+    // the properties which this rule sets to something other than the default
+    pub(crate) fn declared(&self) -> Vec<&'static str> {
+        let mut declared = style_properties(&self.style).into_iter()
+            .filter(|(.., is_set)| *is_set)
+            .map(|(property, ..)| property)
+            .collect::<Vec<&'static str>>();
+
+        let node = NodeBundle::default();
+        let button = ButtonBundle::default();
+
+        if self.node.background_color.0 != node.background_color.0 || self.button.image.color != button.image.color {
+            declared.push("background-color");
+        }
+
+        if self.node.border_color.0 != node.border_color.0 || self.button.border_color.0 != button.border_color.0 {
+            declared.push("border-color");
+        }
+
+        if let Some(section) = self.text.text.sections.first() {
+            let text = TextStyle::default();
+            if section.style.color != text.color { declared.push("color"); }
+            if section.style.font_size != text.font_size { declared.push("font-size"); }
+        }
+
+        // e.g. margin-left: calc(...) doesn't change Style::margin, it's only recorded here
+        for property in self.lengths.keys() {
+            if !declared.contains(property) { declared.push(property); }
+        }
+
+        if self.font.family.is_some() { declared.push("font-family"); }
+        if self.font.weight.is_some() { declared.push("font-weight"); }
+        if self.font.style.is_some() { declared.push("font-style"); }
+        if self.text_properties.white_space.is_some() { declared.push("white-space"); }
+        if self.text_properties.line_height.is_some() { declared.push("line-height"); }
+        if self.text_properties.text_overflow.is_some() { declared.push("text-overflow"); }
+        if self.text_properties.text_transform.is_some() { declared.push("text-transform"); }
+        if self.text_properties.text_align.is_some() { declared.push("text-align"); }
+        if self.text_properties.decoration_line.is_some() { declared.push("text-decoration-line"); }
+        if self.text_properties.decoration_color.is_some() { declared.push("text-decoration-color"); }
+        if self.text_properties.decoration_thickness.is_some() { declared.push("text-decoration-thickness"); }
+
+        // including color: currentColor, which might not differ from the default
+        for property in self.current_color.keys() {
+            let property = property.trim_end_matches("-thumb").trim_end_matches("-track");
+            if !declared.contains(&property) { declared.push(property); }
+        }
+
+        if self.scroll.position.is_some() && !declared.contains(&"position") { declared.push("position"); }
+        if self.scroll.overflow_x.is_some() { declared.push("overflow-x"); }
+        if self.scroll.overflow_y.is_some() { declared.push("overflow-y"); }
+        if self.scroll.behavior.is_some() { declared.push("scroll-behavior"); }
+        if self.scroll.margin_top.is_some() { declared.push("scroll-margin-top"); }
+        if self.scroll.scrollbar_color.is_some() && !declared.contains(&"scrollbar-color") { declared.push("scrollbar-color"); }
+        if self.scroll.scrollbar_width.is_some() { declared.push("scrollbar-width"); }
+        if self.transform.transform.is_some() { declared.push("transform"); }
+        if self.transform.origin.is_some() { declared.push("transform-origin"); }
+
+        if self.node.border_radius != node.border_radius || self.button.border_radius != button.border_radius {
+            declared.push("border-radius");
+        }
+
+        if self.effects.opacity.is_some() { declared.push("opacity"); }
+        if self.effects.filter.is_some() { declared.push("filter"); }
+        if self.transition.property.is_some() { declared.push("transition-property"); }
+        if self.transition.duration.is_some() { declared.push("transition-duration"); }
+        if self.transition.timing_function.is_some() { declared.push("transition-timing-function"); }
+        if self.transition.delay.is_some() { declared.push("transition-delay"); }
+        if self.animation.name.is_some() { declared.push("animation-name"); }
+        if self.animation.duration.is_some() { declared.push("animation-duration"); }
+        if self.animation.timing_function.is_some() { declared.push("animation-timing-function"); }
+        if self.animation.delay.is_some() { declared.push("animation-delay"); }
+        if self.animation.iteration_count.is_some() { declared.push("animation-iteration-count"); }
+        if self.animation.direction.is_some() { declared.push("animation-direction"); }
+        if self.animation.fill_mode.is_some() { declared.push("animation-fill-mode"); }
+        if self.animation.play_state.is_some() { declared.push("animation-play-state"); }
+
+        let outline = Outline::default();
+        if self.outline.width != outline.width || self.outline.offset != outline.offset || self.outline.color != outline.color {
+            declared.push("outline");
+        }
+
+        if !matches!(self.node.z_index, ZIndex::Local(0)) || !matches!(self.button.z_index, ZIndex::Local(0)) {
+            declared.push("z-index");
+        }
+
+        declared
+    }
+}
+
+macro_rules! properties {
+    ($style:ident, $initial:ident, $($property:literal => $field:ident),* $(,)?) => {
+        vec![$(($property, format!("{:?}", $style.$field), $style.$field != $initial.$field)),*]
+    };
+}
+
+// (CSS property, value, whether the value differs from the initial value) for every field of Style
+fn style_properties(style: &Style) -> Vec<(&'static str, String, bool)> {
+    let initial = Style::default();
+    properties!(style, initial,
+        "display" => display,
+        "position" => position_type,
+        "overflow" => overflow,
+        "direction" => direction,
+        "left" => left,
+        "right" => right,
+        "top" => top,
+        "bottom" => bottom,
+        "width" => width,
+        "height" => height,
+        "min-width" => min_width,
+        "min-height" => min_height,
+        "max-width" => max_width,
+        "max-height" => max_height,
+        "aspect-ratio" => aspect_ratio,
+        "align-items" => align_items,
+        "justify-items" => justify_items,
+        "align-self" => align_self,
+        "justify-self" => justify_self,
+        "align-content" => align_content,
+        "justify-content" => justify_content,
+        "margin" => margin,
+        "padding" => padding,
+        "border-width" => border,
+        "flex-direction" => flex_direction,
+        "flex-wrap" => flex_wrap,
+        "flex-grow" => flex_grow,
+        "flex-shrink" => flex_shrink,
+        "flex-basis" => flex_basis,
+        "row-gap" => row_gap,
+        "column-gap" => column_gap,
+        "grid-auto-flow" => grid_auto_flow,
+        "grid-template-rows" => grid_template_rows,
+        "grid-template-columns" => grid_template_columns,
+        "grid-auto-rows" => grid_auto_rows,
+        "grid-auto-columns" => grid_auto_columns,
+        "grid-row" => grid_row,
+        "grid-column" => grid_column,
+    )
+}
+
+// press I to dump the ComputedStyle of the topmost element under the cursor, like "Inspect element"
+pub(crate) fn inspect(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    window: Query<&Window>,
+    elements: Query<(Entity, &Node, &GlobalTransform, &Classes, &ComputedStyle)>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyI) { return; }
+    let Some(cursor) = window.single().cursor_position() else { return; };
+
+    let element = elements.iter()
+        .filter(|(_, node, transform, ..)| node.logical_rect(transform).contains(cursor))
+        .max_by_key(|(_, node, ..)| node.stack_index());
+
+    if let Some((entity, node, _, classes, computed)) = element {
+        info!("{:?} {} ({} x {})\n{}", entity, classes.selector(), node.size().x, node.size().y, computed.dump());
+    }
+}
@@ -1,13 +1,71 @@
+use std::collections::BTreeMap;
+
+use bevy::ecs::query::QueryData;
+use bevy::hierarchy::HierarchyQueryExt;
 use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
 use bevy::window::WindowResized;
 
-use crate::{Link, when_debugging};
+use crate::Link;
+use crate::animation::{AnimationProperties, Animations, Keyframe, Keyframes};
+use crate::calc::{self, Length};
+use crate::computed::ComputedStyle;
+use crate::declarations::{self, Declaration};
+use crate::effects::Effects;
+use crate::fonts::{FontProperties, Fonts};
+use crate::html::Tag;
 use crate::patch::Patch;
+use crate::scroll::{Position, ScrollProperties};
+use crate::text::{self, TextProperties};
+use crate::transform::{CssTransform, TransformProperties};
+use crate::transition::{TransitionProperties, Transitions};
 
 // cannot apply multiple components of the same type to a single entity, so group Vec<Class> into Classes
 #[derive(Component)]
 pub(crate) struct Classes(pub Vec<Class>);
 
+// element.classList
+impl Classes {
+    pub(crate) fn add(&mut self, class: Class) {
+        if !self.contains(&class) { self.0.push(class); }
+    }
+
+    pub(crate) fn remove(&mut self, class: &Class) {
+        self.0.retain(|each| each != class);
+    }
+
+    // returns true if the class is now present
+    pub(crate) fn toggle(&mut self, class: Class) -> bool {
+        if self.contains(&class) {
+            self.remove(&class);
+            false
+        } else {
+            self.add(class);
+            true
+        }
+    }
+
+    pub(crate) fn contains(&self, class: &Class) -> bool {
+        self.0.contains(class)
+    }
+
+    // e.g. ".button.button--pink"
+    pub(crate) fn selector(&self) -> String {
+        self.0.iter().map(|each| each.selector()).collect()
+    }
+}
+
+// style="..." attribute, applied after all Classes (inline specificity)
+// FIXME parsed when the element is spawned, so var()s in it keep the values they had then
+#[derive(Component, Default)]
+pub(crate) struct InlineStyle(pub(crate) Styles);
+
+impl InlineStyle {
+    pub(crate) fn parse(css: &CSS, style: &str) -> Self {
+        Self(declarations::styles(css, "style attribute", &declarations::parse(style)))
+    }
+}
+
 // literal --css-variables and also responsive CSS (usually depends on @media(min-width))
 #[derive(Resource)]
 pub struct CSS {
@@ -16,9 +74,34 @@ pub struct CSS {
     pub(crate) logo_height: Val, // depends on @media(min-width)
     pub(crate) header_height: Val, // var(--header-height), depends on @media(min-width)
     pub(crate) header_padding: UiRect, // depends on @media(min-width)
-    pub(crate) fira_sans: Handle<Font>,
+    pub(crate) fonts: Fonts,
     pub(crate) github_img_height: Val, // depends on @media(min-width)
     pub(crate) main_menu_link_font_size: f32, // navbar becomes hamburger menu on small screens
+    rules: Rules, // parsed by recalculate()
+}
+
+// every rule of the stylesheet, parsed against the current --css-variables, so that cascading an element
+// doesn't parse (and report) them all again
+#[derive(Default)]
+struct Rules {
+    user_agent: HashMap<&'static str, Styles>,
+    classes: HashMap<Class, Styles>,
+    pseudo: HashMap<(Class, Class), Styles>,
+    keyframes: HashMap<&'static str, Keyframes>,
+}
+
+impl Rules {
+    fn parse(css: &CSS) -> Self {
+        let user_agent = USER_AGENT.iter().filter_map(|tag| Some((*tag, css.user_agent_rule(tag)?))).collect();
+        let classes = Class::ALL.iter().map(|class| (*class, css.styles(class))).collect();
+        let pseudo = Class::ALL.iter()
+            .flat_map(|class| Class::PSEUDO.iter().map(move |pseudo| (*class, *pseudo)))
+            .filter_map(|(class, pseudo)| Some(((class, pseudo), css.pseudo_styles(&class, &pseudo)?)))
+            .collect();
+        let keyframes = KEYFRAMES.iter().filter_map(|name| Some((*name, css.keyframes_rule(name)?))).collect();
+
+        Rules { user_agent, classes, pseudo, keyframes }
+    }
 }
 
 impl Default for CSS {
@@ -29,14 +112,15 @@ impl Default for CSS {
             logo_height: Val::Px(28.),
             header_height: Val::Px(60.),
             header_padding: UiRect::right(Val::Px(8.)),
-            fira_sans: Handle::default(),
+            fonts: Fonts::default(),
             github_img_height: Val::Px(35.),
             main_menu_link_font_size: 1.3 * 19.5, // default: 1.3 * rem
+            rules: Rules::default(),
         }
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) enum Class {
     LayoutHeader, // layout__header
     HeaderLogo, // header__logo
@@ -46,18 +130,87 @@ pub(crate) enum Class {
     Logo, // logo
     HeaderMessage, // header__message
     MainMenuEntry, // main-menu__entry
-    MainMenuLinkText, // main-menu__link
     HeaderCtaContainer, // header__cta-container
     ButtonPink, // button--pink
     Button, // button
     ButtonIcon, // button__icon
-    ButtonText, // button
     HeaderCtaGitHub, // header__cta--github
     HeaderCtaGitHubImg, // header__cta--github > img
+    LayoutContent, // layout__content
+    Feature, // feature
+    FeatureTitle, // feature__title
+    FeatureText, // feature__text
+
+    // pseudo-classes are toggled on and off like any other class
+    Hover, // :hover
+    Focus, // :focus
+    FocusVisible, // :focus-visible
 }
 
+impl Class {
+    const ALL: [Class; 21] = [
+        Class::LayoutHeader, Class::HeaderLogo, Class::MainMenuLink, Class::HeaderContent, Class::HeaderLeftBlock,
+        Class::Logo, Class::HeaderMessage, Class::MainMenuEntry, Class::HeaderCtaContainer,
+        Class::ButtonPink, Class::Button, Class::ButtonIcon, Class::HeaderCtaGitHub,
+        Class::HeaderCtaGitHubImg, Class::LayoutContent, Class::Feature, Class::FeatureTitle, Class::FeatureText,
+        Class::Hover, Class::Focus, Class::FocusVisible,
+    ];
+    const PSEUDO: [Class; 3] = [Class::Hover, Class::Focus, Class::FocusVisible];
+
+    // used to name the rule which set a property, see ComputedStyle
+    pub(crate) fn selector(&self) -> &'static str {
+        match self {
+            Class::LayoutHeader => ".layout__header",
+            Class::HeaderLogo => ".header__logo",
+            Class::MainMenuLink => ".main-menu__link",
+            Class::HeaderContent => ".header__content",
+            Class::HeaderLeftBlock => ".header__left-block",
+            Class::Logo => ".logo",
+            Class::HeaderMessage => ".header__message",
+            Class::MainMenuEntry => ".main-menu__entry",
+            Class::HeaderCtaContainer => ".header__cta-container",
+            Class::ButtonPink => ".button--pink",
+            Class::Button => ".button",
+            Class::ButtonIcon => ".button__icon",
+            Class::HeaderCtaGitHub => ".header__cta--github",
+            Class::HeaderCtaGitHubImg => ".header__cta--github img",
+            Class::LayoutContent => ".layout__content",
+            Class::Feature => ".feature",
+            Class::FeatureTitle => ".feature__title",
+            Class::FeatureText => ".feature__text",
+            Class::Hover => ":hover",
+            Class::Focus => ":focus",
+            Class::FocusVisible => ":focus-visible",
+        }
+    }
+}
+
+// every tag which the user agent stylesheet has a rule for, see CSS::user_agent_rule
+const USER_AGENT: [&str; 6] = ["strong", "b", "em", "i", "code", "a"];
+
+// the names of every @keyframes rule, see CSS::keyframes_rule
+const KEYFRAMES: [&str; 1] = ["pulse"];
+
 impl CSS {
-    pub(crate) fn styles(&self, class: &Class) -> Styles {
+    // @keyframes by animation-name
+    pub(crate) fn keyframes(&self, name: &str) -> Option<&Keyframes> {
+        self.rules.keyframes.get(name)
+    }
+
+    // like the user agent stylesheet in a browser, which every other rule overrides
+    fn user_agent_rule(&self, tag: &str) -> Option<Styles> {
+        match tag {
+            "strong" | "b" => Some(self.declare(tag, "font-weight: bold;")),
+            "em" | "i" => Some(self.declare(tag, "font-style: italic;")),
+            "code" => Some(self.declare(tag, "font-family: monospace;")),
+            "a" => Some(self.declare(tag, "text-decoration: underline;")),
+            _ => None
+        }
+    }
+
+    fn styles(&self, class: &Class) -> Styles {
+        let rule = class.selector();
+
         match class {
             Class::LayoutHeader => {
                 // .layout__header {
@@ -69,36 +222,27 @@ impl CSS {
                 //     border-bottom: 2px solid #2c2c2d;
                 //     z-index: 800;
                 // }
-                Styles {
-                    style: Style {
-                        position_type: PositionType::Absolute,
-                        top: Val::Px(0.),
-                        width: Val::Percent(100.),
-                        height: self.header_height,
-                        border: UiRect::bottom(Val::Px(2.0)),
-                        padding: self.header_padding,
-                        ..default()
-                    },
-                    node: NodeBundle {
-                        background_color: Srgba::hex("#1e1e22").unwrap().into(),
-                        border_color: Srgba::hex("#2c2c2d").unwrap().into(),
-                        ..default()
-                    },
-                    ..default()
-                }
+                self.declare(rule, "
+                    position: fixed;
+                    top: 0px;
+                    width: 100%;
+                    height: var(--header-height);
+                    background-color: #1e1e22;
+                    border-bottom: 2px solid #2c2c2d;
+                    z-index: 800;
+                    padding: var(--header-padding);
+                    font-family: 'Fira Sans', sans-serif; /* from body, which isn't rendered */
+                ")
             }
             Class::HeaderLogo => {
                 // .header__logo {
                 //     display: flex;
                 //     align-items: center;
                 // }
-                Styles {
-                    style: Style {
-                        align_items: AlignItems::Center,
-                        ..default()
-                    },
-                    ..default()
-                }
+                self.declare(rule, "
+                    display: flex;
+                    align-items: center;
+                ")
             }
             Class::MainMenuLink => {
                 // .main-menu__link {
@@ -110,18 +254,19 @@ impl CSS {
                 //     font-size: 1.3rem;
                 //     font-weight: 500;
                 //     text-decoration: none;
+                //     color: #ececec;
                 // }
-                Styles {
-                    style: Style {
-                        align_items: AlignItems::Center,
-                        justify_content: JustifyContent::Center,
-                        padding: UiRect::new(Val::Px(7.), Val::Px(7.), Val::Px(0.), Val::Px(0.)), // FIXME font is too wide, reduce padding to compensate
-                        height: self.header_height,
-                        border: when_debugging(UiRect::all(Val::Px(1.))),
-                        ..default()
-                    },
-                    ..default()
-                }
+                self.declare(rule, "
+                    display: flex;
+                    align-items: center;
+                    justify-content: center;
+                    padding: 0 7px; /* FIXME font is too wide, reduce padding to compensate */
+                    height: var(--header-height);
+                    font-size: var(--main-menu-link-font-size);
+                    font-weight: 500;
+                    text-decoration: none;
+                    color: #ececec;
+                ")
             }
             Class::HeaderContent => {
                 // .header__content {
@@ -133,18 +278,15 @@ impl CSS {
                 //     justify-content: space-between;
                 //     margin: 0 auto;
                 // }
-                Styles {
-                    style: Style {
-                        width: Val::Percent(100.),
-                        max_width: Val::Px(1200.),
-                        height: self.header_height,
-                        align_items: AlignItems::Center,
-                        justify_content: JustifyContent::SpaceBetween,
-                        margin: UiRect::new(Val::Auto, Val::Auto, Val::ZERO, Val::ZERO),
-                        ..default()
-                    },
-                    ..default()
-                }
+                self.declare(rule, "
+                    width: 100%;
+                    max-width: 1200px;
+                    height: var(--header-height);
+                    display: flex;
+                    align-items: center;
+                    justify-content: space-between;
+                    margin: 0 auto;
+                ")
             }
             Class::HeaderLeftBlock => {
                 // .header__left-block {
@@ -152,30 +294,21 @@ impl CSS {
                 //     align-items: center;
                 //     margin-right: auto;
                 // }
-                Styles {
-                    style: Style {
-                        align_items: AlignItems::Center,
-                        margin: UiRect::right(Val::Auto),
-                        // height: Val::Percent(100.),
-                        // width: Val::Px(520.0), // required by child node
-                        ..default()
-                    },
-                    ..default()
-                }
+                self.declare(rule, "
+                    display: flex;
+                    align-items: center;
+                    margin-right: auto;
+                ")
             }
             Class::Logo => {
                 // .logo {
                 //     height: 28px;
                 //     width: auto;
                 // }
-                Styles {
-                    style: Style {
-                        height: self.logo_height,
-                        width: Val::Auto,
-                        ..default()
-                    },
-                    ..default()
-                }
+                self.declare(rule, "
+                    height: var(--logo-height);
+                    width: auto;
+                ")
             }
             Class::HeaderMessage => {
                 // .header__message {
@@ -186,73 +319,25 @@ impl CSS {
                 //     margin-left: 12px;
                 //     white-space: nowrap;
                 // }
-                Styles {
-                    style: Style {
-                        margin: UiRect::left(Val::Px(11.)),
-                        ..default()
-                    },
-                    text: TextBundle {
-                        text: Text::from_section(
-                            "",
-                            TextStyle {
-                                font: self.fira_sans.clone(),
-                                font_size: self.header_message_font_size,
-                                color: Srgba::hex("#868686").unwrap().into(),
-                                ..default()
-                            }
-                        ),
-                        transform: Transform::from_scale(Vec3::new(0.97, 1.0, 1.0)),
-                        ..default()
-                    },
-                    ..default()
-                }
-            }
-            Class::MainMenuEntry => {
-                Styles {
-                    style: Style {
-                        border: when_debugging(UiRect::all(Val::Px(1.))),
-                        ..default()
-                    },
-                    node: NodeBundle {
-                        border_color: when_debugging(Srgba::RED.into()),
-                        // transform: Transform::from_scale(Vec3::new(0.97, 1.0, 1.0)),
-                        ..default()
-                    },
-                    ..default()
-                }
-            }
-            Class::MainMenuLinkText => {
-                Styles {
-                    text: TextBundle {
-                        text: Text::from_section(
-                            "",
-                            TextStyle {
-                                font: self.fira_sans.clone(),
-                                font_size: self.main_menu_link_font_size,
-                                color: Srgba::hex("#ececec").unwrap().into(),
-                                ..default()
-                            }
-                        ),
-                        transform: Transform::from_scale(Vec3::new(0.97, 1.0, 1.0)),
-                        ..default()
-                    },
-                    ..default()
-                }
+                self.declare(rule, "
+                    font-size: var(--header-message-font-size);
+                    font-weight: 500;
+                    color: #868686;
+                    white-space: nowrap;
+                ")
             }
+            Class::MainMenuEntry => Styles::default(),
             Class::HeaderCtaContainer => {
                 // .header__cta-container {
                 //     display: flex;
                 //     align-items: center;
                 //     margin-left: 16px;
                 // }
-                Styles {
-                    style: Style {
-                        align_items: AlignItems::Center,
-                        margin: UiRect::left(Val::Px(16.)),
-                        ..default()
-                    },
-                    ..default()
-                }
+                self.declare(rule, "
+                    display: flex;
+                    align-items: center;
+                    margin-left: 16px;
+                ")
             }
             Class::ButtonPink => {
                 // .header__cta:not(:last-child) {
@@ -262,18 +347,11 @@ impl CSS {
                 //     background-color: #9f517a;
                 //     border-color: #ba789b;
                 // }
-                Styles {
-                    style: Style {
-                        margin: UiRect::right(Val::Px(8.)),
-                        ..default()
-                    },
-                    button: ButtonBundle {
-                        image: UiImage::default().with_color(Srgba::hex("#9f517a").unwrap().into()),
-                        border_color: Srgba::hex("#ba789b").unwrap().into(),
-                        ..default()
-                    },
-                    ..default()
-                }
+                self.declare(rule, "
+                    margin-right: 8px;
+                    background-color: #9f517a;
+                    border-color: #ba789b;
+                ")
             }
             Class::Button => {
                 // .button {
@@ -287,16 +365,16 @@ impl CSS {
                 //     font-weight: 500;
                 //     transition: transform 100ms;
                 // }
-                // FIXME Bevy doesn't yet support border-radius, see: https://github.com/bevyengine/bevy/pull/8973
-                Styles {
-                    style: Style {
-                        align_items: AlignItems::Center,
-                        border: UiRect::all(Val::Px(3.)),
-                        padding: UiRect::px(7., 7., 5.5, 6.5),
-                        ..default()
-                    },
-                    ..default()
-                }
+                self.declare(rule, "
+                    align-items: center;
+                    border: 3px solid #6a8fb3;
+                    border-radius: 10px;
+                    padding: 5.5px 7px 6.5px; /* FIXME font is too tall, adjust padding to compensate */
+                    font-size: 1.2rem;
+                    font-weight: 500;
+                    transition: transform 100ms;
+                    text-decoration: none; /* the site's reset stylesheet does this for every a */
+                ")
             }
             Class::ButtonIcon => {
                 // .button__icon {
@@ -306,52 +384,11 @@ impl CSS {
                 //     margin-left: 0.2em;
                 //     font-size: .9em;
                 // }
-                Styles {
-                    style: Style {
-                        height: Val::Px(1.1 * 17.4), // TODO define em as 17.4
-                        margin: UiRect::left(Val::Px(0.2 * self.rem)),
-                        ..default()
-                    },
-                    text: TextBundle {
-                        text: Text::from_section(
-                            "",
-                            TextStyle {
-                                font_size: 0.9 * self.rem,
-                                ..default()
-                            }
-                        ),
-                        ..default()
-                    },
-                    ..default()
-                }
-            }
-            Class::ButtonText => {
-                // .button {
-                //     display: inline-flex;
-                //     align-items: center;
-                //     background-color: #4a6e91;
-                //     border: 3px solid #6a8fb3;
-                //     padding: 6px 8px;
-                //     border-radius: 10px;
-                //     font-size: 1.2rem;
-                //     font-weight: 500;
-                //     transition: transform 100ms;
-                // }
-                // FIXME Bevy doesn't yet support border-radius, see: https://github.com/bevyengine/bevy/pull/8973
-                Styles {
-                    text: TextBundle {
-                        text: Text::from_section(
-                            "",
-                            TextStyle {
-                                font: self.fira_sans.clone(),
-                                font_size: 1.2 * self.rem,
-                                ..default()
-                            }
-                        ),
-                        ..default()
-                    },
-                    ..default()
-                }
+                self.declare(rule, "
+                    height: 19.14px; /* TODO define em as 17.4, this is 1.1em */
+                    margin-left: 0.2rem;
+                    font-size: 0.9rem;
+                ")
             }
             Class::HeaderCtaGitHub => {
                 // .header__cta--github {
@@ -359,30 +396,150 @@ impl CSS {
                 //     align-items: center;
                 //     justify-content: center;
                 // }
-                Styles {
-                    style: Style {
-                        align_items: AlignItems::Center,
-                        justify_content: JustifyContent::Center,
-                        ..default()
-                    },
-                    ..default()
-                }
+                self.declare(rule, "
+                    display: flex;
+                    align-items: center;
+                    justify-content: center;
+                    filter: brightness(0.8); /* FIXME the icon is white, so it can only brighten on hover if it's dimmed first */
+                ")
             }
             Class::HeaderCtaGitHubImg => {
                 // .header__cta--github img {
                 //     height: 35px;
                 // }
-                Styles {
-                    style: Style {
-                        height: self.github_img_height,
-                        width: Val::Auto,
-                        ..default()
-                    },
-                    ..default()
-                }
+                self.declare(rule, "
+                    height: var(--github-img-height);
+                    width: auto;
+                ")
+            }
+            // the page below the header is a stand-in, so the rest of these rules aren't from bevyengine.org
+            Class::LayoutContent => {
+                self.declare(rule, "
+                    flex-direction: column;
+                    align-items: center;
+                    flex-grow: 1;
+                    min-height: 0; /* FIXME Bevy has no scroll containers, which can shrink below their contents */
+                    overflow-y: auto;
+                    scroll-behavior: smooth;
+                    padding: var(--header-height) 16px 48px;
+                    scrollbar-color: #6a8fb3 #1e1e22;
+                    font-family: 'Fira Sans', sans-serif;
+                ")
+            }
+            Class::Feature => {
+                self.declare(rule, "
+                    flex-direction: column;
+                    width: 100%;
+                    max-width: 800px;
+                    margin-top: 48px;
+                    scroll-margin-top: var(--header-height);
+                ")
+            }
+            Class::FeatureTitle => {
+                self.declare(rule, "
+                    font-size: 1.8rem;
+                    font-weight: 600;
+                    color: #ececec;
+                    margin-bottom: 8px;
+                ")
+            }
+            Class::FeatureText => {
+                self.declare(rule, "
+                    font-size: 1.1rem;
+                    color: #b0b0b0;
+                    line-height: 1.5;
+                ")
+            }
+            Class::Hover => Styles::default(),
+            Class::Focus => Styles::default(),
+            Class::FocusVisible => {
+                // from the user agent stylesheet, which in Chrome is
+                // :focus-visible {
+                //     outline: -webkit-focus-ring-color auto 1px;
+                // }
+                self.declare(rule, "
+                    outline: 2px solid #b1d9ff;
+                    outline-offset: 2px;
+                ")
             }
         }
     }
+
+    // rules with a pseudo-class, e.g. .button--pink:hover, which only apply when the element also has that pseudo-class
+    fn pseudo_styles(&self, class: &Class, pseudo: &Class) -> Option<Styles> {
+        match (class, pseudo) {
+            (Class::MainMenuLink, Class::Hover) => {
+                Some(self.declare(".main-menu__link:hover", "
+                    color: #b1d9ff;
+                "))
+            }
+            (Class::HeaderCtaGitHub, Class::Hover) => {
+                Some(self.declare(".header__cta--github:hover", "
+                    filter: brightness(1);
+                "))
+            }
+            (Class::Button, Class::Hover) => {
+                Some(self.declare(".button:hover", "
+                    transform: scale(1.05);
+                "))
+            }
+            (Class::ButtonPink, Class::Hover) => {
+                Some(self.declare(".button--pink:hover", "
+                    background-color: #954c72;
+                "))
+            }
+            _ => None
+        }
+    }
+
+    fn keyframes_rule(&self, name: &str) -> Option<Keyframes> {
+        match name {
+            // not in the original stylesheet, e.g. for style="animation: pulse 2s infinite"
+            "pulse" => {
+                Some(self.declare_keyframes("@keyframes pulse", "
+                    50% { opacity: 0.5; }
+                "))
+            }
+            _ => None
+        }
+    }
+
+    // like declare(), for the block of a @keyframes rule
+    #[track_caller]
+    fn declare_keyframes(&self, rule: &str, block: &str) -> Keyframes {
+        Keyframes::parse(self, rule, std::panic::Location::caller().line() as usize, block)
+    }
+
+    // a rule written as a CSS declaration block which starts on the same line as the call,
+    // so that diagnostics can report lines in this file
+    #[track_caller]
+    fn declare(&self, rule: &str, block: &str) -> Styles {
+        let line = std::panic::Location::caller().line() as usize;
+
+        let declarations = declarations::parse(block).into_iter()
+            .map(|declaration| Declaration { line: line + declaration.line - 1, ..declaration })
+            .collect::<Vec<Declaration>>();
+
+        declarations::styles(self, rule, &declarations)
+    }
+
+    // var(--css-variables) used in declarations
+    pub(crate) fn var(&self, name: &str) -> Option<String> {
+        let px = |px: f32| declarations::serialize(Val::Px(px));
+
+        match name {
+            "--header-height" => Some(declarations::serialize(self.header_height)),
+            "--header-padding" => {
+                let UiRect { left, right, top, bottom } = self.header_padding;
+                Some([top, right, bottom, left].map(declarations::serialize).join(" "))
+            }
+            "--logo-height" => Some(declarations::serialize(self.logo_height)),
+            "--github-img-height" => Some(declarations::serialize(self.github_img_height)),
+            "--header-message-font-size" => Some(px(self.header_message_font_size)),
+            "--main-menu-link-font-size" => Some(px(self.main_menu_link_font_size)),
+            _ => None
+        }
+    }
 }
 
 pub(crate) fn recalculate(
@@ -431,6 +588,9 @@ pub(crate) fn recalculate(
         // FIXME -- scale to 0 here to simulate navbar collapsing to hamburger menu in CSS
         _ => 0.
     };
+
+    // with the new --css-variables
+    css.rules = Rules::parse(&css);
 }
 
 pub(crate) fn a_hover(
@@ -461,75 +621,231 @@ pub(crate) fn a_hover(
 
 }
 
-pub(crate) fn main_menu_link_hover(
-    link_interaction: Query<(&Interaction, &Children, &Classes), Changed<Interaction>>,
-    mut query_text: Query<&mut Text>,
+// toggle :hover on links, restyle() takes care of the rest
+pub(crate) fn hover(
+    mut links: Query<(&Interaction, &mut Classes), (Changed<Interaction>, With<Link>)>,
 ) {
-    for (interaction, children, classes) in link_interaction.iter() {
-        if !classes.0.contains(&Class::MainMenuLink) { continue; }
+    for (interaction, mut classes) in links.iter_mut() {
+        match interaction {
+            Interaction::Hovered | Interaction::Pressed => classes.add(Class::Hover),
+            Interaction::None => classes.remove(&Class::Hover),
+        }
+    }
+}
 
-        let hovered_color = Srgba::hex("#b1d9ff").unwrap().into();
-        let normal_color = Srgba::hex("#ececec").unwrap().into();
+// the components which draw an element, see draw()
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub(crate) struct Drawable {
+    style: Option<&'static mut Style>, // inline elements like <strong> have no Node, see html::p
+    text: Option<&'static mut Text>,
+    background_color: Option<&'static mut BackgroundColor>,
+    border_color: Option<&'static mut BorderColor>,
+    border_radius: Option<&'static mut BorderRadius>,
+    image: Option<&'static mut UiImage>,
+    outline: Option<&'static mut Outline>,
+    css_transform: Option<&'static mut CssTransform>,
+    is_button: Has<Button>,
+}
 
-        match interaction {
-            Interaction::Hovered | Interaction::Pressed => {
-                for child in children {
-                    if let Ok(mut text) = query_text.get_mut(*child) {
-                        text.sections[0].style.color = hovered_color;
-                    }
-                }
+type Element = (
+    &'static Tag,
+    &'static Classes,
+    &'static InlineStyle,
+    &'static mut ComputedStyle,
+    Option<&'static mut Transitions>,
+    Option<&'static mut Animations>,
+    Drawable,
+);
+
+// recompute the styles of every element whose Classes changed, along with all of its descendants,
+// so that e.g. toggling :hover on a link also recolors the text inside of it
+pub(crate) fn restyle(
+    mut commands: Commands,
+    css: Res<CSS>,
+    time: Res<Time>,
+    changed: Query<Entity, Changed<Classes>>,
+    parents: Query<&Parent>,
+    children: Query<&Children>,
+    mut elements: Query<Element>,
+) {
+    let changed = changed.iter().collect::<HashSet<Entity>>();
+
+    for entity in changed.iter() {
+        // if an ancestor also changed, this entity will be restyled along with it
+        if parents.iter_ancestors(*entity).any(|ancestor| changed.contains(&ancestor)) { continue; }
+
+        // color, font-size, etc. are inherited from the parent, which has already been restyled
+        let inherited = parents.get(*entity).ok()
+            .and_then(|parent| elements.get(parent.get()).ok())
+            .map(|(_, _, _, computed, ..)| computed.clone())
+            .unwrap_or_default();
+
+        restyle_recursive(&mut commands, &css, time.elapsed_seconds(), *entity, &inherited, &children, &mut elements);
+    }
+}
+
+fn restyle_recursive(
+    commands: &mut Commands,
+    css: &CSS,
+    now: f32,
+    entity: Entity,
+    inherited: &ComputedStyle,
+    children: &Query<&Children>,
+    elements: &mut Query<Element>,
+) {
+    let mut inherited = inherited.clone();
+
+    if let Ok((tag, classes, inline, mut computed, transitions, animations, mut drawable)) = elements.get_mut(entity) {
+        let is_button = drawable.is_button;
+        let previous = std::mem::replace(&mut *computed, ComputedStyle::cascade(css, css.rules(tag, classes, inline), &inherited, is_button));
+
+        // keyframes are cascaded after every other rule of the element
+        let keyframe = |keyframe: &Keyframe| {
+            let mut rules = css.rules(tag, classes, inline);
+            rules.push((keyframe.rule.clone(), &keyframe.styles));
+            ComputedStyle::cascade(css, rules, &inherited, is_button)
+        };
+
+        match (transitions, animations) {
+            (Some(mut transitions), Some(mut animations)) => {
+                transitions.start(&previous, &computed, now);
+                animations.update(css, &computed, now, keyframe);
             }
-            Interaction::None => {
-                for child in children {
-                    if let Ok(mut text) = query_text.get_mut(*child) {
-                        text.sections[0].style.color = normal_color;
-                    }
-                }
+            // the first style of a new element isn't a change, so nothing transitions, but it does start animations
+            _ => {
+                let mut animations = Animations::default();
+                animations.update(css, &computed, now, keyframe);
+                commands.entity(entity).insert((Transitions::default(), animations));
             }
         }
+
+        // the text itself is styled by text::sections
+        if let Some(text) = drawable.text.as_mut() { text::restyle(text, &computed); }
+        draw(&computed, drawable);
+
+        inherited = computed.clone();
+    }
+
+    if let Ok(entity_children) = children.get(entity) {
+        for child in entity_children.iter() {
+            restyle_recursive(commands, css, now, *child, &inherited, children, elements);
+        }
     }
 }
 
-pub(crate) fn button_pink_hover(
-    mut link_interaction: Query<(&Interaction, &mut UiImage, &Classes), Changed<Interaction>>,
-) {
-    for (interaction, mut background_color, classes) in link_interaction.iter_mut() {
-        if !classes.0.contains(&Class::ButtonPink) { continue; }
+// write a ComputedStyle to the components which draw it, from css::restyle, or animation::animate while a
+// property is on its way from one value to another
+pub(crate) fn draw(computed: &ComputedStyle, drawable: DrawableItem<'_>) {
+    let DrawableItem { style, text, background_color, border_color, border_radius, image, outline, css_transform, is_button } = drawable;
 
-        let hovered_color = Srgba::hex("#954c72").unwrap().into();
-        let normal_color = Srgba::hex("#9f517a").unwrap().into();
+    // only write when something changed, so that Bevy doesn't redo the layout (or the rendering) every frame
+    if let Some(mut style) = style {
+        let mut drawn = computed.style.clone();
 
-        match interaction {
-            Interaction::Hovered | Interaction::Pressed => {
-                *background_color = UiImage::default().with_color(hovered_color);
-            }
-            Interaction::None => {
-                *background_color = UiImage::default().with_color(normal_color);
+        // a sticky element's insets are where it sticks (see scroll::stick), rather than how far it's moved
+        if computed.scroll.position() == Position::Sticky {
+            (drawn.top, drawn.right, drawn.bottom, drawn.left) = (Val::Auto, Val::Auto, Val::Auto, Val::Auto);
+        }
+
+        // calc(), em, etc. are whatever calc::evaluate made of them last
+        if !computed.lengths.is_empty() {
+            let mut current = style.clone();
+            for property in computed.lengths.keys() {
+                if let (Some(drawn), Some(current)) = (calc::field(&mut drawn, property), calc::field(&mut current, property)) {
+                    *drawn = *current;
+                }
             }
         }
+
+        if *style != drawn { *style = drawn; }
+    }
+
+    let (background, border) = (computed.with_opacity(computed.background_color), computed.with_opacity(computed.border_color));
+    if is_button {
+        if let Some(mut image) = image { if image.color != background { image.color = background; } }
+        if let Some(mut border_color) = border_color { border_color.set_if_neq(BorderColor(border)); }
+    } else if let Some(mut image) = image {
+        // <img>, whose filter is applied to the image itself by svg::rasterize
+        let color = computed.with_opacity(Color::WHITE);
+        if image.color != color { image.color = color; }
+    } else if text.is_none() {
+        if let Some(mut background_color) = background_color { background_color.set_if_neq(BackgroundColor(background)); }
+        if let Some(mut border_color) = border_color { border_color.set_if_neq(BorderColor(border)); }
+    }
+
+    if let Some(mut border_radius) = border_radius {
+        let mut drawn = computed.border_radius;
+        let lengths = &computed.lengths;
+        if lengths.contains_key("border-top-left-radius") { drawn.top_left = border_radius.top_left; }
+        if lengths.contains_key("border-top-right-radius") { drawn.top_right = border_radius.top_right; }
+        if lengths.contains_key("border-bottom-right-radius") { drawn.bottom_right = border_radius.bottom_right; }
+        if lengths.contains_key("border-bottom-left-radius") { drawn.bottom_left = border_radius.bottom_left; }
+        border_radius.set_if_neq(drawn);
+    }
+    if let Some(mut outline) = outline { outline.set_if_neq(Outline { color: computed.with_opacity(computed.outline.color), ..computed.outline }); }
+    if let Some(mut css_transform) = css_transform {
+        if css_transform.properties != computed.transform { css_transform.properties = computed.transform.clone(); }
     }
 }
 
 // ---
 
+#[derive(Default)]
 pub(crate) struct Styles {
     pub(crate) style: Style,
     pub(crate) node: NodeBundle,
     pub(crate) text: TextBundle,
-    pub(crate) button: ButtonBundle
+    pub(crate) button: ButtonBundle,
+    // font-family, font-weight and font-style, since TextStyle only has the resolved Handle<Font>
+    pub(crate) font: FontProperties,
+    // white-space, line-height and text-overflow
+    pub(crate) text_properties: TextProperties,
+    // every <length> by property, including the ones which Val can't represent, see calc::Length
+    pub(crate) lengths: BTreeMap<&'static str, Length>,
+    pub(crate) outline: Outline,
+    // opacity and filter
+    pub(crate) effects: Effects,
+    pub(crate) transition: TransitionProperties,
+    pub(crate) animation: AnimationProperties,
+    pub(crate) transform: TransformProperties,
+    // overflow, which Style only has as clip or visible, and the scrollbar
+    pub(crate) scroll: ScrollProperties,
+    // color properties by whether they're currentColor, see ComputedStyle::cascade
+    pub(crate) current_color: BTreeMap<&'static str, bool>,
 }
 
-impl Default for Styles {
-    fn default() -> Self {
+impl Styles {
+    // the initial value of every property, before any rules have been applied
+    pub(crate) fn initial() -> Self {
         Self {
-            style: Default::default(),
-            node: Default::default(),
-            text: Default::default(),
             button: ButtonBundle {
                 // default button background color is white -- it should be clear
-                image: UiImage::default().with_color(Color::NONE).into(),
+                image: UiImage::default().with_color(Color::NONE),
                 ..default()
-            }
+            },
+            ..default()
+        }
+    }
+}
+
+impl Patch for Styles {
+    fn apply(&self, patch: &Self) -> Self {
+        Styles {
+            style: self.style.apply(&patch.style),
+            node: self.node.apply(&patch.node),
+            text: self.text.apply(&patch.text),
+            button: self.button.apply(&patch.button),
+            font: self.font.apply(&patch.font),
+            text_properties: self.text_properties.apply(&patch.text_properties),
+            lengths: self.lengths.apply(&patch.lengths),
+            outline: self.outline.apply(&patch.outline),
+            effects: self.effects.apply(&patch.effects),
+            transition: self.transition.apply(&patch.transition),
+            animation: self.animation.apply(&patch.animation),
+            transform: self.transform.apply(&patch.transform),
+            scroll: self.scroll.apply(&patch.scroll),
+            current_color: self.current_color.apply(&patch.current_color),
         }
     }
 }
@@ -558,12 +874,58 @@ impl From<Styles> for ButtonBundle {
     }
 }
 
+impl CSS {
+    // every rule which applies to an element, in cascade order, along with its selector:
+    // the user agent's rule for the tag, then plain classes, then classes with pseudo-classes, then the style
+    // attribute (increasing specificity)
+    pub(crate) fn rules<'a>(&'a self, tag: &Tag, classes: &'a Classes, inline: &'a InlineStyle) -> Vec<(String, &'a Styles)> {
+        let user_agent = self.rules.user_agent.get(tag.0)
+            .map(|styles| (format!("{} (user agent)", tag.0), styles));
+
+        let plain = classes.0.iter()
+            .filter_map(|each| Some((each.selector().to_string(), self.rules.classes.get(each)?)));
+
+        let pseudo = classes.0.iter().flat_map(|pseudo| {
+            classes.0.iter().filter_map(move |each| {
+                let styles = self.rules.pseudo.get(&(*each, *pseudo))?;
+                Some((format!("{}{}", each.selector(), pseudo.selector()), styles))
+            })
+        });
+
+        let inline = std::iter::once(("style attribute".to_string(), &inline.0));
+
+        user_agent.into_iter().chain(plain).chain(pseudo).chain(inline).collect()
+    }
+}
+
 pub(crate) trait Cascading {
-    fn cascade(css: &CSS, classes: &Classes) -> Self;
+    fn cascade(css: &CSS, tag: &Tag, classes: &Classes, inline: &InlineStyle) -> Self;
+}
+
+impl<T: From<Styles>> Cascading for T {
+    fn cascade(css: &CSS, tag: &Tag, classes: &Classes, inline: &InlineStyle) -> Self {
+        css.rules(tag, classes, inline).into_iter()
+            .fold(Styles::initial(), |acc, (_, patch)| acc.apply(patch))
+            .into()
+    }
 }
 
-impl<T: From<Styles> + Patch + Default> Cascading for T {
-    fn cascade(css: &CSS, classes: &Classes) -> Self {
-        classes.0.iter().map(|each| css.styles(each).into()).fold(Self::default(), |acc, patch| acc.apply(&patch))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle() {
+        let mut classes = Classes(vec![Class::Button]);
+
+        assert!(classes.toggle(Class::Hover));
+        assert!(classes.contains(&Class::Hover));
+        assert!(!classes.toggle(Class::Hover));
+        assert!(!classes.contains(&Class::Hover));
+
+        // the other classes stay where they were
+        assert!(!classes.toggle(Class::Button));
+        assert!(classes.toggle(Class::Button));
+        assert_eq!(classes.selector(), ".button");
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::computed::ComputedStyle;
+use crate::css::Classes;
+use crate::html::Tag;
+
+// same colors as the box model diagram in browser devtools
+const MARGIN: Color = Color::Srgba(Srgba { red: 0.976, green: 0.800, blue: 0.616, alpha: 0.6 });
+const BORDER: Color = Color::Srgba(Srgba { red: 0.992, green: 0.867, blue: 0.608, alpha: 0.6 });
+const PADDING: Color = Color::Srgba(Srgba { red: 0.765, green: 0.816, blue: 0.545, alpha: 0.6 });
+const CONTENT: Color = Color::Srgba(Srgba { red: 0.549, green: 0.714, blue: 0.753, alpha: 0.4 });
+
+// outlines the margin, border, padding and content of every element when enabled
+// press D to toggle, or build with `--features debug` to start with it enabled
+#[derive(Resource)]
+pub(crate) struct DebugOverlay {
+    pub(crate) enabled: bool,
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self { enabled: cfg!(feature = "debug") }
+    }
+}
+
+// The overlay is drawn with UI nodes on top of everything else. It's spawned the first time it's enabled, and its
+// nodes are kept in step with the elements rather than rebuilt, like text::decorate does.
+#[derive(Component)]
+pub(crate) struct DebugOverlayRoot {
+    boxes: HashMap<Entity, [Entity; 3]>, // element => the nodes of its margin, border and padding
+    label: Entity,
+}
+
+pub(crate) fn toggle(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut overlay: ResMut<DebugOverlay>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyD) {
+        overlay.enabled = !overlay.enabled;
+    }
+}
+
+pub(crate) fn draw(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    mut roots: Query<(Entity, &mut DebugOverlayRoot, &mut Style)>,
+    mut layers: Query<&mut Style, (Without<DebugOverlayRoot>, Without<Text>)>,
+    mut labels: Query<(&mut Style, &mut Text, &mut Visibility), Without<DebugOverlayRoot>>,
+    window: Query<&Window>,
+    elements: Query<(Entity, &Node, &GlobalTransform, &ComputedStyle, &Tag, &Classes, Option<&Parent>)>,
+    nodes: Query<&Node>,
+) {
+    let Ok((root, mut state, mut root_style)) = roots.get_single_mut() else {
+        if overlay.enabled { spawn(&mut commands); }
+        return;
+    };
+
+    // hidden rather than despawned, so that it's there for next time
+    let display = if overlay.enabled { Display::Flex } else { Display::None };
+    if root_style.display != display { root_style.display = display; }
+    if !overlay.enabled { return; }
+
+    let window = window.single();
+    let viewport = Vec2::new(window.width(), window.height());
+    let cursor = window.cursor_position();
+
+    let mut hovered = None;
+    let mut drawn = HashSet::new();
+
+    for (entity, node, transform, computed, tag, classes, node_parent) in elements.iter() {
+        if node.size() == Vec2::ZERO { continue; }
+
+        // percentages in margin and padding are relative to the width of the parent
+        let parent_width = node_parent
+            .and_then(|node_parent| nodes.get(node_parent.get()).ok())
+            .map(|parent_node| parent_node.size().x)
+            .unwrap_or(viewport.x);
+
+        let to_px = |rect: UiRect| UiRect::px(
+            resolve(rect.left, parent_width, viewport),
+            resolve(rect.right, parent_width, viewport),
+            resolve(rect.top, parent_width, viewport),
+            resolve(rect.bottom, parent_width, viewport),
+        );
+
+        let border_box = node.logical_rect(transform);
+        let styles = box_model(border_box, to_px(computed.style.margin), to_px(computed.style.border), to_px(computed.style.padding));
+
+        match state.boxes.get(&entity) {
+            Some(nodes) => for (node, style) in nodes.iter().zip(styles) {
+                if let Ok(mut layer) = layers.get_mut(*node) { layer.set_if_neq(style); }
+            },
+            None => {
+                let nodes = spawn_box_model(&mut commands, root, styles);
+                state.boxes.insert(entity, nodes);
+            }
+        }
+        drawn.insert(entity);
+
+        if cursor.is_some_and(|cursor| border_box.contains(cursor)) {
+            let is_topmost = hovered.as_ref().map_or(true, |(stack_index, _)| node.stack_index() > *stack_index);
+            if is_topmost {
+                let label = format!("{}{}  {:.0} × {:.0}", tag.0, classes.selector(), node.size().x, node.size().y);
+                hovered = Some((node.stack_index(), label));
+            }
+        }
+    }
+
+    // elements which are gone, or have no size any more
+    state.boxes.retain(|element, nodes| {
+        if !drawn.contains(element) { commands.entity(nodes[0]).despawn_recursive(); }
+        drawn.contains(element)
+    });
+
+    // label the topmost element under the cursor
+    let Ok((mut style, mut text, mut visibility)) = labels.get_mut(state.label) else { return; };
+    match (cursor, hovered) {
+        (Some(cursor), Some((_, label))) => {
+            style.set_if_neq(Style { left: Val::Px(cursor.x + 12.), top: Val::Px(cursor.y + 12.), ..label_style() });
+            if text.sections[0].value != label { text.sections[0].value = label; }
+            visibility.set_if_neq(Visibility::Inherited);
+        }
+        _ => { visibility.set_if_neq(Visibility::Hidden); }
+    }
+}
+
+fn spawn(commands: &mut Commands) {
+    let label = commands.spawn(TextBundle {
+        style: label_style(),
+        text: Text::from_section("", TextStyle { font_size: 14., ..default() }),
+        background_color: Color::BLACK.into(),
+        visibility: Visibility::Hidden,
+        z_index: ZIndex::Local(1), // above the boxes, which are added after it
+        ..default()
+    }).id();
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            z_index: ZIndex::Global(i32::MAX),
+            ..default()
+        },
+        DebugOverlayRoot { boxes: HashMap::new(), label }
+    )).add_child(label);
+}
+
+fn label_style() -> Style {
+    Style {
+        position_type: PositionType::Absolute,
+        padding: UiRect::all(Val::Px(4.)),
+        ..default()
+    }
+}
+
+// the styles of nested nodes, each one's border is one layer of the box model
+fn box_model(border_box: Rect, margin: UiRect, border: UiRect, padding: UiRect) -> [Style; 3] {
+    let px = |val: Val| if let Val::Px(px) = val { px } else { 0. };
+
+    let layer = |border: UiRect| Style {
+        width: Val::Percent(100.),
+        height: Val::Percent(100.),
+        border,
+        ..default()
+    };
+
+    let margin_box = Style {
+        position_type: PositionType::Absolute,
+        left: Val::Px(border_box.min.x - px(margin.left)),
+        top: Val::Px(border_box.min.y - px(margin.top)),
+        width: Val::Px(border_box.width() + px(margin.left) + px(margin.right)),
+        height: Val::Px(border_box.height() + px(margin.top) + px(margin.bottom)),
+        ..layer(margin)
+    };
+
+    [margin_box, layer(border), layer(padding)]
+}
+
+fn spawn_box_model(commands: &mut Commands, root: Entity, [margin, border, padding]: [Style; 3]) -> [Entity; 3] {
+    let content = commands.spawn(NodeBundle {
+        style: Style {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            ..default()
+        },
+        background_color: CONTENT.into(),
+        ..default()
+    }).id();
+
+    let padding = commands.spawn(NodeBundle { style: padding, border_color: PADDING.into(), ..default() }).add_child(content).id();
+    let border = commands.spawn(NodeBundle { style: border, border_color: BORDER.into(), ..default() }).add_child(padding).id();
+    let margin = commands.spawn(NodeBundle { style: margin, border_color: MARGIN.into(), ..default() }).add_child(border).set_parent(root).id();
+
+    [margin, border, padding]
+}
+
+// auto margins are resolved by the layout, so they are drawn as 0 here
+fn resolve(val: Val, parent_width: f32, viewport: Vec2) -> f32 {
+    let px = match val {
+        Val::Px(px) => px,
+        Val::Percent(percent) => percent / 100. * parent_width,
+        Val::Vw(vw) => vw / 100. * viewport.x,
+        Val::Vh(vh) => vh / 100. * viewport.y,
+        Val::VMin(vmin) => vmin / 100. * viewport.min_element(),
+        Val::VMax(vmax) => vmax / 100. * viewport.max_element(),
+        Val::Auto => 0.,
+    };
+    px.max(0.)
+}
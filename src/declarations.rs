@@ -0,0 +1,899 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use bevy::prelude::*;
+
+use crate::animation::{Direction, FillMode, PlayState};
+use crate::calc::{self, Length};
+use crate::color::{self, CssColor};
+use crate::css::{CSS, Styles};
+use crate::effects;
+use crate::fonts::FontStyle;
+use crate::transition::{self, CubicBezier};
+use crate::scroll::{Overflow, Position, ScrollBehavior, ScrollbarWidth};
+use crate::transform;
+use crate::text::{DecorationLine, LineHeight, TextOverflow, TextTransform, WhiteSpace};
+
+// a single `property: value` pair, e.g. from a style="..." attribute
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Declaration {
+    pub(crate) property: String,
+    pub(crate) value: String,
+    pub(crate) line: usize, // within the declaration block, starting at 1
+}
+
+// reported (instead of panicking) when a declaration cannot be applied
+pub(crate) struct Diagnostic<'a> {
+    pub(crate) rule: &'a str,
+    pub(crate) line: usize,
+    pub(crate) message: String,
+}
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, line {}: {}", self.rule, self.line, self.message)
+    }
+}
+
+// split a declaration block like "margin-left: 11px; color: #868686" into Declarations
+pub(crate) fn parse(block: &str) -> Vec<Declaration> {
+    let mut line = 1;
+    let mut declarations = vec![];
+
+    for each in strip_comments(block).split(';') {
+        // a declaration starts on the first line of its chunk which isn't blank
+        let leading = &each[..each.len() - each.trim_start().len()];
+        let declaration_line = line + leading.matches('\n').count();
+        line += each.matches('\n').count();
+
+        let Some((property, value)) = each.split_once(':') else { continue; };
+        let property = property.trim().to_lowercase();
+        let value = value.trim();
+
+        if property.is_empty() || value.is_empty() { continue; }
+
+        declarations.push(Declaration { property, value: value.into(), line: declaration_line });
+    }
+
+    declarations
+}
+
+// replace /* comments */ with blank lines, so that line numbers are unaffected
+pub(crate) fn strip_comments(block: &str) -> String {
+    let mut stripped = String::new();
+    let mut rest = block;
+
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        let end = rest[start..].find("*/").map_or(rest.len(), |end| start + end + 2);
+        stripped.extend(rest[start..end].chars().filter(|each| *each == '\n'));
+        rest = &rest[end..];
+    }
+
+    stripped.push_str(rest);
+    stripped
+}
+
+// convert a list of Declarations into a Styles patch, which can then be cascaded like any Class
+// `rule` names where the declarations came from, for diagnostics
+pub(crate) fn styles(css: &CSS, rule: &str, declarations: &[Declaration]) -> Styles {
+    let mut styles = Styles::default();
+
+    for declaration in declarations {
+        let result = substitute(css, &declaration.value)
+            .and_then(|value| expand(&declaration.property, &value))
+            .and_then(|longhands| longhands.iter().try_for_each(|(property, value)| declare(css, &mut styles, property, value)));
+
+        if let Err(message) = result {
+            warn!("{}", Diagnostic { rule, line: declaration.line, message });
+        }
+    }
+
+    styles
+}
+
+// the longhand of every property which some declarations set, e.g. `margin: 0` => margin-top, margin-right, ...
+pub(crate) fn longhands(css: &CSS, declarations: &[Declaration]) -> Vec<String> {
+    declarations.iter()
+        .filter_map(|declaration| substitute(css, &declaration.value).and_then(|value| expand(&declaration.property, &value)).ok())
+        .flatten()
+        .map(|(property, _)| property)
+        .collect()
+}
+
+// replace every var(--name) or var(--name, fallback) with the value of that --css-variable
+fn substitute(css: &CSS, value: &str) -> Result<String, String> {
+    let mut value = value.to_string();
+
+    while let Some(start) = value.find("var(") {
+        let end = start + value[start..].find(')').ok_or("unclosed var(")?;
+
+        let (name, fallback) = match value[start + 4..end].split_once(',') {
+            Some((name, fallback)) => (name.trim(), Some(fallback.trim())),
+            None => (value[start + 4..end].trim(), None),
+        };
+
+        let replacement = css.var(name)
+            .or(fallback.map(String::from))
+            .ok_or_else(|| format!("undefined variable `{}`", name))?;
+
+        value.replace_range(start..=end, &replacement);
+    }
+
+    Ok(value)
+}
+
+// split a value on whitespace, but not inside of parentheses, e.g. "3px solid rgb(1, 2, 3)"
+pub(crate) fn tokens(value: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut depth = 0;
+    let mut start = None;
+
+    for (index, each) in value.char_indices() {
+        match each {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if each.is_whitespace() && depth == 0 => {
+                if let Some(start) = start.take() { tokens.push(&value[start..index]); }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(index);
+    }
+
+    if let Some(start) = start { tokens.push(&value[start..]); }
+    tokens
+}
+
+// a comma-separated list, e.g. "color 1s, transform 100ms cubic-bezier(0, 0, 1, 1)" => ["color 1s", "transform ..."]
+pub(crate) fn list(value: &str) -> Vec<&str> {
+    let mut items = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (index, each) in value.char_indices() {
+        match each {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(value[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    items.push(value[start..].trim());
+    items
+}
+
+// 1 to 4 values => [top, right, bottom, left], like margin and padding
+fn sides<'a>(tokens: &[&'a str]) -> Result<[&'a str; 4], String> {
+    match *tokens {
+        [all] => Ok([all, all, all, all]),
+        [vertical, horizontal] => Ok([vertical, horizontal, vertical, horizontal]),
+        [top, horizontal, bottom] => Ok([top, horizontal, bottom, horizontal]),
+        [top, right, bottom, left] => Ok([top, right, bottom, left]),
+        _ => Err(format!("expected 1 to 4 values, found {}", tokens.len())),
+    }
+}
+
+const SIDES: [&str; 4] = ["top", "right", "bottom", "left"];
+
+// in the same order as border-radius values
+const CORNERS: [&str; 4] = ["top-left", "top-right", "bottom-right", "bottom-left"];
+
+const BORDER_STYLES: [&str; 10] = ["none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset", "outset"];
+
+const TEXT_DECORATION_STYLES: [&str; 5] = ["solid", "double", "dotted", "dashed", "wavy"];
+
+const FONT_WEIGHTS: [&str; 4] = ["normal", "bold", "bolder", "lighter"];
+
+// shorthand properties expand into their longhands, anything else is passed through unchanged
+fn expand(property: &str, value: &str) -> Result<Vec<(String, String)>, String> {
+    let tokens = tokens(value);
+    let longhand = |property: String, value: &str| (property, value.to_string());
+
+    let longhands = match property {
+        "margin" | "padding" => {
+            SIDES.iter().zip(sides(&tokens)?)
+                .map(|(side, value)| longhand(format!("{}-{}", property, side), value))
+                .collect()
+        }
+        "inset" => {
+            SIDES.iter().zip(sides(&tokens)?)
+                .map(|(side, value)| longhand(side.to_string(), value))
+                .collect()
+        }
+        "border-width" => {
+            SIDES.iter().zip(sides(&tokens)?)
+                .map(|(side, value)| longhand(format!("border-{}-width", side), value))
+                .collect()
+        }
+        "border" | "border-top" | "border-right" | "border-bottom" | "border-left" => {
+            let (mut width, mut style, mut border_color) = ("medium", "solid", None);
+            for token in tokens {
+                if BORDER_STYLES.contains(&token) { style = token; }
+                else if color::parse(token).is_ok() { border_color = Some(token); }
+                else { width = token; }
+            }
+
+            // a border with no style isn't drawn at all
+            let width = if style == "none" || style == "hidden" { "0" } else { width };
+            let edges = match property.strip_prefix("border-") {
+                Some(side) => vec![side],
+                None => SIDES.to_vec(),
+            };
+
+            let mut longhands = edges.into_iter()
+                .map(|side| longhand(format!("border-{}-width", side), width))
+                .collect::<Vec<_>>();
+
+            // FIXME Bevy only has one border color per node, so border-top etc. color all sides
+            if let Some(border_color) = border_color {
+                longhands.push(longhand("border-color".into(), border_color));
+            }
+            longhands
+        }
+        "outline" => {
+            let (mut width, mut style, mut outline_color) = ("medium", "solid", None);
+            for token in tokens {
+                if BORDER_STYLES.contains(&token) || token == "auto" { style = token; }
+                else if color::parse(token).is_ok() { outline_color = Some(token); }
+                else { width = token; }
+            }
+
+            let mut longhands = vec![longhand("outline-width".into(), width), longhand("outline-style".into(), style)];
+            if let Some(outline_color) = outline_color {
+                longhands.push(longhand("outline-color".into(), outline_color));
+            }
+            longhands
+        }
+        "border-radius" => {
+            // FIXME Bevy corners are circular, so no elliptical radii like "10px / 20px"
+            if tokens.contains(&"/") { Err("elliptical corners are not supported")? }
+            CORNERS.iter().zip(sides(&tokens)?)
+                .map(|(corner, value)| longhand(format!("border-{}-radius", corner), value))
+                .collect()
+        }
+        "border-color" => {
+            match *tokens.as_slice() {
+                [single] => vec![longhand(property.into(), single)],
+                _ => Err("Bevy only supports a single border color")?
+            }
+        }
+        "border-style" => {
+            match *tokens.as_slice() {
+                ["none"] | ["hidden"] => SIDES.iter().map(|side| longhand(format!("border-{}-width", side), "0")).collect(),
+                ["solid"] => vec![],
+                _ => Err("only solid borders are supported")?
+            }
+        }
+        "font" => {
+            // [ <font-style> || <font-weight> ]? <font-size> [ / <line-height> ]? <font-family>
+            let mut longhands = vec![];
+            let mut rest = tokens.iter();
+
+            for token in rest.by_ref() {
+                if matches!(*token, "italic" | "oblique") {
+                    longhands.push(longhand("font-style".into(), *token));
+                } else if FONT_WEIGHTS.contains(token) || token.parse::<u16>().is_ok() {
+                    longhands.push(longhand("font-weight".into(), *token));
+                } else if *token == "small-caps" {
+                    // ignored, along with font-stretch
+                } else {
+                    match token.split_once('/') {
+                        Some((size, line_height)) => {
+                            longhands.push(longhand("font-size".into(), size));
+                            longhands.push(longhand("line-height".into(), line_height));
+                        }
+                        None => longhands.push(longhand("font-size".into(), *token)),
+                    }
+                    break;
+                }
+            }
+
+            let family = rest.copied().collect::<Vec<&str>>().join(" ");
+            if family.is_empty() { Err("font requires a font-size and a font-family")? }
+            longhands.push(longhand("font-family".into(), &family));
+            longhands
+        }
+        "background" => {
+            // Bevy nodes only have a background color, so no images, gradients, etc.
+            match *tokens.as_slice() {
+                ["none"] => vec![],
+                [single] if color::parse(single).is_ok() => vec![longhand("background-color".into(), single)],
+                _ => Err("only a single background color is supported")?
+            }
+        }
+        "flex" => {
+            let is_number = |token: &str| token.parse::<f32>().is_ok();
+            let [grow, shrink, basis] = match *tokens.as_slice() {
+                ["none"] => ["0", "0", "auto"].map(String::from),
+                ["auto"] => ["1", "1", "auto"].map(String::from),
+                ["initial"] => ["0", "1", "auto"].map(String::from),
+                [grow] if is_number(grow) => [grow, "1", "0%"].map(String::from),
+                [basis] => ["1", "1", basis].map(String::from),
+                [grow, shrink] if is_number(shrink) => [grow, shrink, "0%"].map(String::from),
+                [grow, basis] => [grow, "1", basis].map(String::from),
+                [grow, shrink, basis] => [grow, shrink, basis].map(String::from),
+                _ => Err(format!("expected 1 to 3 values, found {}", tokens.len()))?
+            };
+            vec![("flex-grow".into(), grow), ("flex-shrink".into(), shrink), ("flex-basis".into(), basis)]
+        }
+        "overflow" => {
+            match *tokens.as_slice() {
+                [both] => vec![longhand("overflow-x".into(), both), longhand("overflow-y".into(), both)],
+                [x, y] => vec![longhand("overflow-x".into(), x), longhand("overflow-y".into(), y)],
+                _ => Err(format!("expected 1 or 2 values, found {}", tokens.len()))?
+            }
+        }
+        "text-decoration" => {
+            // <line> || <style> || <color> || <thickness>, in any order
+            let mut line = vec![];
+            let mut longhands = vec![];
+            for token in tokens {
+                if matches!(token, "none" | "underline" | "overline" | "line-through") { line.push(token); }
+                else if TEXT_DECORATION_STYLES.contains(&token) { longhands.push(longhand("text-decoration-style".into(), token)); }
+                else if color::parse(token).is_ok() { longhands.push(longhand("text-decoration-color".into(), token)); }
+                else { longhands.push(longhand("text-decoration-thickness".into(), token)); }
+            }
+
+            if line.is_empty() { Err("text-decoration requires a line, e.g. underline")? }
+            longhands.push(longhand("text-decoration-line".into(), &line.join(" ")));
+            longhands
+        }
+        "transition" => {
+            // [ <property> || <duration> || <easing-function> || <delay> ]#, the first time is the duration
+            let mut longhands: [Vec<&str>; 4] = Default::default();
+
+            for item in list(value) {
+                let (mut property, mut times, mut easing) = ("all", vec![], "ease");
+                for token in self::tokens(item) {
+                    if transition::time(token).is_ok() { times.push(token); }
+                    else if CubicBezier::parse(token).is_ok() { easing = token; }
+                    else { property = token; }
+                }
+
+                longhands[0].push(property);
+                longhands[1].push(times.first().copied().unwrap_or("0s"));
+                longhands[2].push(easing);
+                longhands[3].push(times.get(1).copied().unwrap_or("0s"));
+            }
+
+            ["transition-property", "transition-duration", "transition-timing-function", "transition-delay"].into_iter()
+                .zip(longhands)
+                .map(|(property, values)| longhand(property.into(), &values.join(", ")))
+                .collect()
+        }
+        "animation" => {
+            // [ <duration> || <easing-function> || <delay> || <iteration-count> || <direction> || <fill-mode>
+            //   || <play-state> || <name> ]#, the first time is the duration
+            let mut longhands: [Vec<&str>; 8] = Default::default();
+
+            for item in list(value) {
+                let (mut name, mut times, mut easing) = ("none", vec![], "ease");
+                let (mut count, mut direction, mut fill_mode, mut play_state) = ("1", "normal", "none", "running");
+
+                for token in self::tokens(item) {
+                    match token {
+                        _ if transition::time(token).is_ok() => times.push(token),
+                        _ if CubicBezier::parse(token).is_ok() => easing = token,
+                        _ if token == "infinite" || token.parse::<f32>().is_ok() => count = token,
+                        "normal" | "reverse" | "alternate" | "alternate-reverse" => direction = token,
+                        "forwards" | "backwards" | "both" => fill_mode = token,
+                        "running" | "paused" => play_state = token,
+                        _ => name = token,
+                    }
+                }
+
+                let times = [times.first().copied().unwrap_or("0s"), times.get(1).copied().unwrap_or("0s")];
+                for (values, value) in longhands.iter_mut().zip([name, times[0], easing, times[1], count, direction, fill_mode, play_state]) {
+                    values.push(value);
+                }
+            }
+
+            [
+                "animation-name", "animation-duration", "animation-timing-function", "animation-delay",
+                "animation-iteration-count", "animation-direction", "animation-fill-mode", "animation-play-state",
+            ].into_iter()
+                .zip(longhands)
+                .map(|(property, values)| longhand(property.into(), &values.join(", ")))
+                .collect()
+        }
+        "gap" => {
+            match *tokens.as_slice() {
+                [both] => vec![longhand("row-gap".into(), both), longhand("column-gap".into(), both)],
+                [row, column] => vec![longhand("row-gap".into(), row), longhand("column-gap".into(), column)],
+                _ => Err(format!("expected 1 or 2 values, found {}", tokens.len()))?
+            }
+        }
+        _ => vec![longhand(property.into(), value)]
+    };
+
+    Ok(longhands)
+}
+
+fn declare(css: &CSS, styles: &mut Styles, property: &str, value: &str) -> Result<(), String> {
+    let invalid = || format!("invalid value `{}` for `{}`", value, property);
+    let style = &mut styles.style;
+
+    match property {
+        "display" => style.display = match value {
+            "flex" => Display::Flex,
+            "grid" => Display::Grid,
+            "none" => Display::None,
+            _ => Err(invalid())?
+        },
+        // Bevy has no "fixed" or "sticky" position, so they're laid out as "absolute" and "relative", and then
+        // transform::apply and scroll::stick move them as things scroll
+        "position" => {
+            let position = match value {
+                "relative" => Position::Relative,
+                "absolute" => Position::Absolute,
+                "fixed" => Position::Fixed,
+                "sticky" => Position::Sticky,
+                _ => Err(invalid())?
+            };
+            style.position_type = match position {
+                Position::Relative | Position::Sticky => PositionType::Relative,
+                Position::Absolute | Position::Fixed => PositionType::Absolute,
+            };
+            styles.scroll.position = Some(position);
+        },
+        "top" => style.top = length_of(css, &mut styles.lengths, property, value)?,
+        "right" => style.right = length_of(css, &mut styles.lengths, property, value)?,
+        "bottom" => style.bottom = length_of(css, &mut styles.lengths, property, value)?,
+        "left" => style.left = length_of(css, &mut styles.lengths, property, value)?,
+        "width" => style.width = length_of(css, &mut styles.lengths, property, value)?,
+        "height" => style.height = length_of(css, &mut styles.lengths, property, value)?,
+        "min-width" => style.min_width = length_of(css, &mut styles.lengths, property, value)?,
+        "min-height" => style.min_height = length_of(css, &mut styles.lengths, property, value)?,
+        "max-width" => style.max_width = length_of(css, &mut styles.lengths, property, value)?,
+        "max-height" => style.max_height = length_of(css, &mut styles.lengths, property, value)?,
+        "margin-top" => style.margin.top = length_of(css, &mut styles.lengths, property, value)?,
+        "margin-right" => style.margin.right = length_of(css, &mut styles.lengths, property, value)?,
+        "margin-bottom" => style.margin.bottom = length_of(css, &mut styles.lengths, property, value)?,
+        "margin-left" => style.margin.left = length_of(css, &mut styles.lengths, property, value)?,
+        "padding-top" => style.padding.top = length_of(css, &mut styles.lengths, property, value)?,
+        "padding-right" => style.padding.right = length_of(css, &mut styles.lengths, property, value)?,
+        "padding-bottom" => style.padding.bottom = length_of(css, &mut styles.lengths, property, value)?,
+        "padding-left" => style.padding.left = length_of(css, &mut styles.lengths, property, value)?,
+        "border-top-width" => style.border.top = length_of(css, &mut styles.lengths, property, value)?,
+        "border-right-width" => style.border.right = length_of(css, &mut styles.lengths, property, value)?,
+        "border-bottom-width" => style.border.bottom = length_of(css, &mut styles.lengths, property, value)?,
+        "border-left-width" => style.border.left = length_of(css, &mut styles.lengths, property, value)?,
+        "border-top-left-radius" => {
+            let radius = length_of(css, &mut styles.lengths, property, value)?;
+            styles.node.border_radius.top_left = radius;
+            styles.button.border_radius.top_left = radius;
+        }
+        "border-top-right-radius" => {
+            let radius = length_of(css, &mut styles.lengths, property, value)?;
+            styles.node.border_radius.top_right = radius;
+            styles.button.border_radius.top_right = radius;
+        }
+        "border-bottom-right-radius" => {
+            let radius = length_of(css, &mut styles.lengths, property, value)?;
+            styles.node.border_radius.bottom_right = radius;
+            styles.button.border_radius.bottom_right = radius;
+        }
+        "border-bottom-left-radius" => {
+            let radius = length_of(css, &mut styles.lengths, property, value)?;
+            styles.node.border_radius.bottom_left = radius;
+            styles.button.border_radius.bottom_left = radius;
+        }
+        // anything but visible is clipped by Bevy, and scrolled by scroll.rs
+        "overflow-x" | "overflow-y" => {
+            let overflow = match value {
+                "visible" => Overflow::Visible,
+                "hidden" => Overflow::Hidden,
+                "clip" => Overflow::Clip,
+                "auto" => Overflow::Auto,
+                "scroll" => Overflow::Scroll,
+                _ => Err(invalid())?
+            };
+            let axis = if overflow == Overflow::Visible { OverflowAxis::Visible } else { OverflowAxis::Clip };
+
+            if property == "overflow-x" {
+                style.overflow.x = axis;
+                styles.scroll.overflow_x = Some(overflow);
+            } else {
+                style.overflow.y = axis;
+                styles.scroll.overflow_y = Some(overflow);
+            }
+        }
+        "flex-direction" => style.flex_direction = match value {
+            "row" => FlexDirection::Row,
+            "column" => FlexDirection::Column,
+            "row-reverse" => FlexDirection::RowReverse,
+            "column-reverse" => FlexDirection::ColumnReverse,
+            _ => Err(invalid())?
+        },
+        "flex-wrap" => style.flex_wrap = match value {
+            "nowrap" => FlexWrap::NoWrap,
+            "wrap" => FlexWrap::Wrap,
+            "wrap-reverse" => FlexWrap::WrapReverse,
+            _ => Err(invalid())?
+        },
+        "flex-grow" => style.flex_grow = value.parse().map_err(|_| invalid())?,
+        "flex-shrink" => style.flex_shrink = value.parse().map_err(|_| invalid())?,
+        "flex-basis" => style.flex_basis = length_of(css, &mut styles.lengths, property, value)?,
+        "row-gap" => style.row_gap = length_of(css, &mut styles.lengths, property, value)?,
+        "column-gap" => style.column_gap = length_of(css, &mut styles.lengths, property, value)?,
+        "align-items" => style.align_items = match value {
+            "flex-start" | "start" => AlignItems::FlexStart,
+            "flex-end" | "end" => AlignItems::FlexEnd,
+            "center" => AlignItems::Center,
+            "baseline" => AlignItems::Baseline,
+            "stretch" => AlignItems::Stretch,
+            _ => Err(invalid())?
+        },
+        "align-self" => style.align_self = match value {
+            "auto" => AlignSelf::Auto,
+            "flex-start" | "start" => AlignSelf::FlexStart,
+            "flex-end" | "end" => AlignSelf::FlexEnd,
+            "center" => AlignSelf::Center,
+            "baseline" => AlignSelf::Baseline,
+            "stretch" => AlignSelf::Stretch,
+            _ => Err(invalid())?
+        },
+        "justify-content" => style.justify_content = match value {
+            "flex-start" | "start" => JustifyContent::FlexStart,
+            "flex-end" | "end" => JustifyContent::FlexEnd,
+            "center" => JustifyContent::Center,
+            "space-between" => JustifyContent::SpaceBetween,
+            "space-around" => JustifyContent::SpaceAround,
+            "space-evenly" => JustifyContent::SpaceEvenly,
+            _ => Err(invalid())?
+        },
+        "background-color" => {
+            let color = resolve_color(styles, "background-color", color::parse(value)?);
+            styles.node.background_color = color.into();
+            styles.button.image = UiImage::default().with_color(color);
+        }
+        "border-color" => {
+            let color = resolve_color(styles, "border-color", color::parse(value)?);
+            styles.node.border_color = color.into();
+            styles.button.border_color = color.into();
+        }
+        "color" => {
+            let color = resolve_color(styles, "color", color::parse(value)?);
+            text_style(styles).color = color;
+        }
+        // anything but px (and rem) is left to calc::evaluate
+        // FIXME so children inherit the parent's font-size from before it was evaluated
+        "font-size" => match length_of(css, &mut styles.lengths, property, value)? {
+            Val::Px(px) => text_style(styles).font_size = px,
+            Val::Auto => Err(invalid())?,
+            _ => {}
+        },
+        "font-family" => {
+            if value.split(',').any(|family| family.trim().is_empty()) { Err(invalid())? }
+            styles.font.family = Some(value.into());
+        }
+        "font-weight" => styles.font.weight = Some(match value {
+            "normal" => 400,
+            "bold" => 700,
+            // FIXME these should be relative to the inherited weight
+            "bolder" => 700,
+            "lighter" => 100,
+            _ => value.parse::<u16>().ok().filter(|weight| (1..=1000).contains(weight)).ok_or_else(invalid)?
+        }),
+        "font-style" => styles.font.style = Some(match value {
+            "normal" => FontStyle::Normal,
+            _ if value == "italic" || value.starts_with("oblique") => FontStyle::Italic,
+            _ => Err(invalid())?
+        }),
+        "white-space" => styles.text_properties.white_space = Some(match value {
+            "normal" => WhiteSpace::Normal,
+            "nowrap" => WhiteSpace::NoWrap,
+            "pre" => WhiteSpace::Pre,
+            "pre-wrap" => WhiteSpace::PreWrap,
+            _ => Err(invalid())?
+        }),
+        "line-height" => styles.text_properties.line_height = match value {
+            "normal" => None,
+            _ => match value.parse::<f32>() {
+                Ok(number) => Some(LineHeight::Number(number)),
+                Err(_) => Some(LineHeight::Length(Length::parse(value)?)),
+            }
+        },
+        "text-overflow" => styles.text_properties.text_overflow = Some(match value {
+            "clip" => TextOverflow::Clip,
+            "ellipsis" => TextOverflow::Ellipsis,
+            _ => Err(invalid())?
+        }),
+        "text-transform" => styles.text_properties.text_transform = Some(match value {
+            "none" => TextTransform::None,
+            "uppercase" => TextTransform::Uppercase,
+            "lowercase" => TextTransform::Lowercase,
+            "capitalize" => TextTransform::Capitalize,
+            _ => Err(invalid())?
+        }),
+        // Text::justify is what Bevy aligns by, text_align is what children inherit
+        "text-align" => {
+            let justify = match value {
+                "left" | "start" => JustifyText::Left,
+                "center" => JustifyText::Center,
+                "right" | "end" => JustifyText::Right,
+                // FIXME Bevy can't justify text
+                _ => Err(invalid())?
+            };
+            styles.text.text.justify = justify;
+            styles.text_properties.text_align = Some(justify);
+        }
+        "text-decoration-line" => styles.text_properties.decoration_line = Some(match value {
+            "none" => DecorationLine::default(),
+            _ => {
+                let mut decoration_line = DecorationLine::default();
+                for token in tokens(value) {
+                    match token {
+                        "underline" => decoration_line.underline = true,
+                        "line-through" => decoration_line.line_through = true,
+                        "overline" => Err("overline is not supported")?,
+                        _ => Err(invalid())?
+                    }
+                }
+                decoration_line
+            }
+        }),
+        "text-decoration-color" => {
+            let color = resolve_color(styles, "text-decoration-color", color::parse(value)?);
+            styles.text_properties.decoration_color = Some(color);
+        }
+        "text-decoration-thickness" => styles.text_properties.decoration_thickness = match value {
+            "auto" | "from-font" => None,
+            _ => Some(Length::parse(value)?),
+        },
+        // FIXME decorations are drawn with nodes, which can only be solid
+        "text-decoration-style" => if value != "solid" { Err(format!("only solid text decorations are supported, not `{}`", value))? },
+        // these don't affect Style, see text::adjust
+        "letter-spacing" | "word-spacing" => {
+            let value = if value == "normal" { "0" } else { value };
+            length_of(css, &mut styles.lengths, property, value)?;
+        }
+        // FIXME only lengths which Val can represent, so no em or calc()
+        "outline-width" | "outline-offset" => {
+            let val = Length::parse(value)?.to_val(css.rem).ok_or_else(|| format!("`{}` only supports px, rem, % and viewport units", property))?;
+            if property == "outline-width" { styles.outline.width = val; } else { styles.outline.offset = val; }
+        }
+        "outline-color" => styles.outline.color = resolve_color(styles, "outline-color", color::parse(value)?),
+        // an outline with no style isn't drawn at all, and auto is whatever the platform draws, which is solid here
+        "outline-style" => match value {
+            "none" | "hidden" => styles.outline.width = Val::ZERO,
+            "solid" | "auto" => {}
+            _ => Err(format!("only solid outlines are supported, not `{}`", value))?
+        },
+        "opacity" => {
+            let opacity = effects::amount(value).ok_or_else(invalid)?;
+            styles.effects.opacity = Some(opacity.clamp(0., 1.));
+        }
+        "filter" => styles.effects.filter = Some(effects::parse_filter(value)?),
+        "scroll-behavior" => styles.scroll.behavior = Some(match value {
+            "auto" => ScrollBehavior::Auto,
+            "smooth" => ScrollBehavior::Smooth,
+            _ => Err(invalid())?
+        }),
+        // FIXME only the top, which is all scrolling to a #fragment needs
+        "scroll-margin-top" => {
+            let val = Length::parse(value)?.to_val(css.rem).ok_or_else(|| format!("`{}` only supports px, rem, % and viewport units", property))?;
+            styles.scroll.margin_top = Some(val);
+        }
+        "scrollbar-color" => styles.scroll.scrollbar_color = match *tokens(value).as_slice() {
+            // FIXME auto should be whatever the platform draws, which is gray here
+            ["auto"] => None,
+            [thumb, track] => Some((
+                resolve_color(styles, "scrollbar-color-thumb", color::parse(thumb)?),
+                resolve_color(styles, "scrollbar-color-track", color::parse(track)?),
+            )),
+            _ => Err(invalid())?
+        },
+        "scrollbar-width" => styles.scroll.scrollbar_width = Some(match value {
+            "auto" => ScrollbarWidth::Auto,
+            "thin" => ScrollbarWidth::Thin,
+            "none" => ScrollbarWidth::None,
+            _ => Err(invalid())?
+        }),
+        "transform" => styles.transform.transform = Some(transform::parse(value, css.rem)?),
+        "transform-origin" => styles.transform.origin = Some(transform::parse_origin(value, css.rem)?),
+        "transition-property" => styles.transition.property = Some(match value {
+            "none" => vec![],
+            _ => list(value).into_iter().map(String::from).collect(),
+        }),
+        "transition-duration" | "transition-delay" | "animation-duration" | "animation-delay" => {
+            let times = list(value).into_iter().map(transition::time).collect::<Result<Vec<f32>, String>>()?;
+            if property.ends_with("-duration") && times.iter().any(|time| *time < 0.) { Err(invalid())? }
+
+            match property {
+                "transition-duration" => styles.transition.duration = Some(times),
+                "transition-delay" => styles.transition.delay = Some(times),
+                "animation-duration" => styles.animation.duration = Some(times),
+                _ => styles.animation.delay = Some(times),
+            }
+        }
+        "transition-timing-function" | "animation-timing-function" => {
+            let easings = list(value).into_iter().map(CubicBezier::parse).collect::<Result<Vec<CubicBezier>, String>>()?;
+            match property {
+                "transition-timing-function" => styles.transition.timing_function = Some(easings),
+                _ => styles.animation.timing_function = Some(easings),
+            }
+        }
+        "animation-name" => styles.animation.name = Some(list(value).into_iter().map(String::from).collect()),
+        "animation-iteration-count" => {
+            styles.animation.iteration_count = Some(list(value).into_iter()
+                .map(|count| match count {
+                    "infinite" => Ok(f32::INFINITY),
+                    _ => count.parse::<f32>().ok().filter(|count| *count >= 0.).ok_or_else(invalid),
+                })
+                .collect::<Result<_, _>>()?);
+        }
+        "animation-direction" => {
+            styles.animation.direction = Some(list(value).into_iter()
+                .map(|direction| match direction {
+                    "normal" => Ok(Direction::Normal),
+                    "reverse" => Ok(Direction::Reverse),
+                    "alternate" => Ok(Direction::Alternate),
+                    "alternate-reverse" => Ok(Direction::AlternateReverse),
+                    _ => Err(invalid()),
+                })
+                .collect::<Result<_, _>>()?);
+        }
+        "animation-fill-mode" => {
+            styles.animation.fill_mode = Some(list(value).into_iter()
+                .map(|fill_mode| match fill_mode {
+                    "none" => Ok(FillMode::None),
+                    "forwards" => Ok(FillMode::Forwards),
+                    "backwards" => Ok(FillMode::Backwards),
+                    "both" => Ok(FillMode::Both),
+                    _ => Err(invalid()),
+                })
+                .collect::<Result<_, _>>()?);
+        }
+        "animation-play-state" => {
+            styles.animation.play_state = Some(list(value).into_iter()
+                .map(|play_state| match play_state {
+                    "running" => Ok(PlayState::Running),
+                    "paused" => Ok(PlayState::Paused),
+                    _ => Err(invalid()),
+                })
+                .collect::<Result<_, _>>()?);
+        }
+        "z-index" => {
+            let z_index = ZIndex::Local(value.parse().map_err(|_| invalid())?);
+            styles.node.z_index = z_index;
+            styles.button.z_index = z_index;
+            styles.text.z_index = z_index;
+        }
+        _ => Err(format!("unsupported property `{}`", property))?
+    }
+
+    Ok(())
+}
+
+// currentColor is the element's computed color, which isn't known until ComputedStyle::cascade, so it's only
+// recorded here, with whatever color this block declares standing in for it until then
+fn resolve_color(styles: &mut Styles, property: &'static str, color: CssColor) -> Color {
+    styles.current_color.insert(property, color == CssColor::CurrentColor);
+
+    match color {
+        CssColor::Color(color) => color,
+        CssColor::CurrentColor => text_style(styles).color,
+    }
+}
+
+// Text patches carry a single, empty section which holds the TextStyle
+fn text_style(styles: &mut Styles) -> &mut TextStyle {
+    if styles.text.text.sections.is_empty() {
+        styles.text.text.sections.push(TextSection::new("", TextStyle::default()));
+    }
+    &mut styles.text.text.sections[0].style
+}
+
+// Parse a CSS <length> or <percentage> into a Val. Anything which Val can't represent, like calc() or em, is
+// recorded in Styles::lengths and 0 until calc::evaluate replaces it. Lengths which Val can represent are
+// recorded too, so that they override an expression from an earlier rule in the cascade.
+fn length_of(css: &CSS, lengths: &mut BTreeMap<&'static str, Length>, property: &str, value: &str) -> Result<Val, String> {
+    let length = Length::parse(value).map_err(|error| format!("invalid value `{}` for `{}`: {}", value, property, error))?;
+    let property = calc::PROPERTIES.iter().find(|each| **each == property).ok_or_else(|| format!("`{}` is not a length", property))?;
+
+    let val = length.to_val(css.rem).unwrap_or(Val::ZERO);
+    lengths.insert(property, length);
+    Ok(val)
+}
+
+// the inverse of length_of(), for --css-variables which are stored as Vals
+pub(crate) fn serialize(val: Val) -> String {
+    match val {
+        Val::Auto => "auto".into(),
+        Val::Px(px) => format!("{}px", px),
+        Val::Percent(percent) => format!("{}%", percent),
+        Val::Vw(vw) => format!("{}vw", vw),
+        Val::Vh(vh) => format!("{}vh", vh),
+        Val::VMin(vmin) => format!("{}vmin", vmin),
+        Val::VMax(vmax) => format!("{}vmax", vmax),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // e.g. ["margin-top: 0", "margin-right: auto", ...]
+    fn expanded(property: &str, value: &str) -> Vec<String> {
+        expand(property, value)
+            .unwrap_or_else(|error| panic!("`{}: {}`: {}", property, value, error))
+            .into_iter()
+            .map(|(property, value)| format!("{}: {}", property, value))
+            .collect()
+    }
+
+    #[test]
+    fn one_to_four_sides() {
+        assert_eq!(expanded("margin", "0 auto"), ["margin-top: 0", "margin-right: auto", "margin-bottom: 0", "margin-left: auto"]);
+        assert_eq!(expanded("padding", "1px 2px 3px"), ["padding-top: 1px", "padding-right: 2px", "padding-bottom: 3px", "padding-left: 2px"]);
+        assert_eq!(expanded("inset", "0"), ["top: 0", "right: 0", "bottom: 0", "left: 0"]);
+        assert_eq!(
+            expanded("border-radius", "1px 2px 3px 4px"),
+            ["border-top-left-radius: 1px", "border-top-right-radius: 2px", "border-bottom-right-radius: 3px", "border-bottom-left-radius: 4px"],
+        );
+    }
+
+    #[test]
+    fn borders() {
+        assert_eq!(
+            expanded("border", "3px solid #6a8fb3"),
+            ["border-top-width: 3px", "border-right-width: 3px", "border-bottom-width: 3px", "border-left-width: 3px", "border-color: #6a8fb3"],
+        );
+        assert_eq!(expanded("border-left", "thick none"), ["border-left-width: 0"]);
+        assert_eq!(expanded("outline", "2px dashed"), ["outline-width: 2px", "outline-style: dashed"]);
+    }
+
+    #[test]
+    fn font_and_flex() {
+        assert_eq!(
+            expanded("font", "italic bold 1.2rem/1.5 Fira Sans"),
+            ["font-style: italic", "font-weight: bold", "font-size: 1.2rem", "line-height: 1.5", "font-family: Fira Sans"],
+        );
+        assert_eq!(expanded("flex", "1"), ["flex-grow: 1", "flex-shrink: 1", "flex-basis: 0%"]);
+        assert_eq!(expanded("flex", "none"), ["flex-grow: 0", "flex-shrink: 0", "flex-basis: auto"]);
+        assert_eq!(expanded("flex", "2 10px"), ["flex-grow: 2", "flex-shrink: 1", "flex-basis: 10px"]);
+    }
+
+    #[test]
+    fn transitions_and_animations() {
+        assert_eq!(
+            expanded("transition", "color 1s, transform 100ms ease-in 50ms"),
+            ["transition-property: color, transform", "transition-duration: 1s, 100ms", "transition-timing-function: ease, ease-in", "transition-delay: 0s, 50ms"],
+        );
+        assert_eq!(
+            expanded("animation", "pulse 2s infinite"),
+            [
+                "animation-name: pulse", "animation-duration: 2s", "animation-timing-function: ease", "animation-delay: 0s",
+                "animation-iteration-count: infinite", "animation-direction: normal", "animation-fill-mode: none", "animation-play-state: running",
+            ],
+        );
+    }
+
+    #[test]
+    fn longhands_pass_through() {
+        assert_eq!(expanded("color", "red"), ["color: red"]);
+        assert_eq!(expanded("margin-left", "11px"), ["margin-left: 11px"]);
+    }
+
+    #[test]
+    fn rejects_unsupported_shorthands() {
+        for (property, value) in [
+            ("margin", "1px 2px 3px 4px 5px"),
+            ("border-radius", "10px / 20px"),
+            ("border-color", "red blue"),
+            ("border-style", "dotted"),
+            ("font", "12px"),
+            ("background", "url(bevy.png)"),
+            ("text-decoration", "red"),
+        ] {
+            assert!(expand(property, value).is_err(), "expected `{}: {}` to be rejected", property, value);
+        }
+    }
+}
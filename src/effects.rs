@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+
+use crate::patch::Patch;
+
+// a single <filter-function>, with its amount, e.g. brightness(1.2)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Filter {
+    Brightness(f32),
+    Grayscale(f32), // 0 to 1
+    Invert(f32), // 0 to 1
+}
+
+// opacity and filter, which Bevy has no equivalent for, None means "not declared"
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Effects {
+    pub(crate) opacity: Option<f32>,
+    pub(crate) filter: Option<Vec<Filter>>, // filter: none is Some(vec![])
+}
+
+impl Patch for Effects {
+    fn apply(&self, patch: &Self) -> Self {
+        Effects {
+            opacity: patch.opacity.or(self.opacity),
+            filter: patch.filter.clone().or(self.filter.clone()),
+        }
+    }
+}
+
+// a number or a percentage, e.g. 0.5 or 50%
+pub(crate) fn amount(value: &str) -> Option<f32> {
+    match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().ok().map(|percent| percent / 100.),
+        None => value.parse::<f32>().ok(),
+    }
+}
+
+// e.g. "brightness(1.2) grayscale(50%)"
+pub(crate) fn parse_filter(value: &str) -> Result<Vec<Filter>, String> {
+    if value == "none" { return Ok(vec![]); }
+
+    let mut filters = vec![];
+    let mut rest = value.trim();
+
+    while !rest.is_empty() {
+        let open = rest.find('(').ok_or_else(|| format!("expected a filter function, found `{}`", rest))?;
+        let close = open + rest[open..].find(')').ok_or("missing `)`")?;
+        let (name, argument) = (rest[..open].trim(), rest[open + 1..close].trim());
+
+        // the amount defaults to 1, i.e. the full effect
+        let amount = match argument {
+            "" => 1.,
+            _ => amount(argument).filter(|amount| *amount >= 0.).ok_or_else(|| format!("invalid amount `{}` for {}()", argument, name))?,
+        };
+
+        filters.push(match name {
+            "brightness" => Filter::Brightness(amount),
+            "grayscale" => Filter::Grayscale(amount.min(1.)),
+            "invert" => Filter::Invert(amount.min(1.)),
+            // FIXME no blur(), contrast(), drop-shadow(), hue-rotate(), opacity(), saturate() or sepia()
+            _ => Err(format!("unsupported filter function `{}()`", name))?
+        });
+
+        rest = rest[close + 1..].trim_start();
+    }
+
+    Ok(filters)
+}
+
+// apply filters to RGBA pixels in place, in order, as in https://www.w3.org/TR/filter-effects-1/#filter-functions
+pub(crate) fn filter(pixels: &mut [u8], filters: &[Filter]) {
+    if filters.is_empty() { return; }
+
+    for pixel in pixels.chunks_exact_mut(4) {
+        let mut rgb = Vec3::new(pixel[0] as f32, pixel[1] as f32, pixel[2] as f32) / 255.;
+
+        for each in filters {
+            rgb = match *each {
+                Filter::Brightness(amount) => rgb * amount,
+                Filter::Grayscale(amount) => {
+                    let gray = rgb.dot(Vec3::new(0.2126, 0.7152, 0.0722));
+                    rgb.lerp(Vec3::splat(gray), amount)
+                }
+                Filter::Invert(amount) => rgb.lerp(Vec3::ONE - rgb, amount),
+            }
+            .clamp(Vec3::ZERO, Vec3::ONE);
+        }
+
+        let rgb = (rgb * 255.).round();
+        pixel[0] = rgb.x as u8;
+        pixel[1] = rgb.y as u8;
+        pixel[2] = rgb.z as u8;
+    }
+}
@@ -0,0 +1,174 @@
+use bevy::a11y::Focus;
+use bevy::prelude::*;
+
+use crate::Link;
+use crate::css::{Class, Classes};
+
+// tabindex="...", links are focusable without one, like in a browser
+#[derive(Component)]
+pub(crate) struct TabIndex(pub(crate) i32);
+
+// whether the focused element should show that it's focused, i.e. whether it was focused from the keyboard
+// rather than by clicking it, see :focus-visible
+#[derive(Resource, Default)]
+pub(crate) struct FocusVisible(pub(crate) bool);
+
+// where the page is, i.e. /path#fragment, which is all there is of a router so far
+#[derive(Resource, Default)]
+pub(crate) struct Location {
+    pub(crate) path: String,
+    pub(crate) fragment: Option<String>, // without the #, scrolled to by scroll::fragment and kept by scroll::spy
+}
+
+impl Location {
+    // where the page starts, the browser's URL on the web, otherwise the first argument, e.g. `cargo run -- /#scenes`
+    pub(crate) fn initial() -> Self {
+        #[cfg(target_arch = "wasm32")]
+        let url = web_sys::window().and_then(|window| {
+            let location = window.location();
+            Some(format!("{}{}", location.pathname().ok()?, location.hash().ok()?))
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        let url = std::env::args().nth(1).filter(|arg| arg.starts_with('/') || arg.starts_with('#'));
+
+        let url = url.unwrap_or_default();
+        let (path, fragment) = split(&url);
+        Location { path: path.into(), fragment: fragment.map(String::from) }
+    }
+}
+
+// e.g. "/learn#quick-start" => ("/learn", Some("quick-start")), an empty fragment is no fragment
+fn split(href: &str) -> (&str, Option<&str>) {
+    match href.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment).filter(|fragment| !fragment.is_empty())),
+        None => (href, None),
+    }
+}
+
+// a link was followed, by clicking it or pressing Enter while it's focused
+#[derive(Event)]
+pub(crate) struct Navigate {
+    pub(crate) href: String,
+}
+
+// Tab and Shift+Tab move the focus through links and elements with a tabindex, positive tabindexes first
+// in increasing order, then the rest in document order. Bevy's Focus is what AccessKit reports to screen readers
+pub(crate) fn tab(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut focus: ResMut<Focus>,
+    mut visible: ResMut<FocusVisible>,
+    roots: Query<Entity, (With<Node>, Without<Parent>)>,
+    children: Query<&Children>,
+    focusable: Query<Option<&TabIndex>, Or<(With<Link>, With<TabIndex>)>>,
+) {
+    if !keyboard.just_pressed(KeyCode::Tab) { return; }
+    let backwards = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    let mut order = vec![];
+    for root in roots.iter() {
+        document_order(root, &children, &mut |entity| {
+            match focusable.get(entity) {
+                Ok(Some(TabIndex(index))) if *index < 0 => {}
+                Ok(tab_index) => order.push((entity, tab_index.map_or(0, |tab_index| tab_index.0))),
+                Err(_) => {}
+            }
+        });
+    }
+
+    // a stable sort, so that elements with the same tabindex stay in document order
+    order.sort_by_key(|(_, index)| if *index > 0 { (0, *index) } else { (1, 0) });
+    if order.is_empty() { return; }
+
+    let current = focus.0.and_then(|focused| order.iter().position(|(entity, _)| *entity == focused));
+    let next = match (current, backwards) {
+        (Some(current), false) => (current + 1) % order.len(),
+        (Some(current), true) => (current + order.len() - 1) % order.len(),
+        (None, false) => 0,
+        (None, true) => order.len() - 1,
+    };
+
+    focus.0 = Some(order[next].0);
+    visible.0 = true;
+}
+
+fn document_order(entity: Entity, children: &Query<&Children>, visit: &mut impl FnMut(Entity)) {
+    visit(entity);
+    for child in children.get(entity).into_iter().flatten() {
+        document_order(*child, children, visit);
+    }
+}
+
+// pressing a link focuses it (without a focus ring), and releasing it over the same link follows it,
+// while pressing anything else drops the focus
+pub(crate) fn click(
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut focus: ResMut<Focus>,
+    mut visible: ResMut<FocusVisible>,
+    mut pressed: Local<Option<Entity>>,
+    links: Query<(Entity, &Interaction, &Link)>,
+    mut navigate: EventWriter<Navigate>,
+) {
+    if mouse.just_pressed(MouseButton::Left) {
+        *pressed = links.iter()
+            .find(|(_, interaction, _)| **interaction == Interaction::Pressed)
+            .map(|(entity, ..)| entity);
+
+        focus.0 = *pressed;
+        visible.0 = false;
+    }
+
+    if mouse.just_released(MouseButton::Left) {
+        if let Some((_, Interaction::Hovered, link)) = pressed.take().and_then(|entity| links.get(entity).ok()) {
+            navigate.send(Navigate { href: link.href.clone() });
+        }
+    }
+}
+
+// Enter follows the focused link
+pub(crate) fn enter(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    focus: Res<Focus>,
+    links: Query<&Link>,
+    mut navigate: EventWriter<Navigate>,
+) {
+    if !keyboard.just_pressed(KeyCode::Enter) { return; }
+
+    if let Some(link) = focus.0.and_then(|focused| links.get(focused).ok()) {
+        navigate.send(Navigate { href: link.href.clone() });
+    }
+}
+
+// toggle :focus and :focus-visible, restyle() takes care of the rest
+pub(crate) fn pseudo_classes(
+    focus: Res<Focus>,
+    visible: Res<FocusVisible>,
+    mut elements: Query<(Entity, &mut Classes)>,
+) {
+    if !focus.is_changed() && !visible.is_changed() { return; }
+
+    for (entity, mut classes) in elements.iter_mut() {
+        let is_focused = focus.0 == Some(entity);
+
+        // only touch Classes when something changes, restyle() reacts to Changed<Classes>
+        for (pseudo, has) in [(Class::Focus, is_focused), (Class::FocusVisible, is_focused && visible.0)] {
+            if classes.contains(&pseudo) != has { classes.toggle(pseudo); }
+        }
+    }
+}
+
+// Links to a #fragment, on this page or another, update the location's fragment so that deep links to headings
+// work, and scroll::fragment scrolls to it
+// TODO there's no router yet, so following a link to another page doesn't go anywhere
+pub(crate) fn navigate(mut events: EventReader<Navigate>, mut location: ResMut<Location>) {
+    for event in events.read() {
+        let (path, fragment) = split(&event.href);
+
+        if !path.is_empty() && path != location.path {
+            info!("navigate to {}", event.href);
+            location.path = path.into();
+        }
+
+        // FIXME this doesn't update the browser's URL on the web
+        location.fragment = fragment.map(String::from);
+    }
+}
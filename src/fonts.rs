@@ -0,0 +1,187 @@
+use std::sync::OnceLock;
+
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy::window::RequestRedraw;
+
+use crate::css::CSS;
+use crate::declarations::{self, Declaration, Diagnostic};
+use crate::patch::Patch;
+use crate::stylesheet;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum FontStyle {
+    #[default]
+    Normal,
+    Italic, // also oblique
+}
+
+// font-family, font-weight and font-style, which together pick a Handle<Font> from Fonts
+// None means "not declared", so that these can be cascaded and inherited like everything else
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct FontProperties {
+    pub(crate) family: Option<String>, // a comma-separated list, e.g. "Fira Sans", sans-serif
+    pub(crate) weight: Option<u16>,
+    pub(crate) style: Option<FontStyle>,
+}
+
+impl Patch for FontProperties {
+    fn apply(&self, patch: &Self) -> Self {
+        FontProperties {
+            family: patch.family.clone().or(self.family.clone()),
+            weight: patch.weight.or(self.weight),
+            style: patch.style.or(self.style),
+        }
+    }
+}
+
+// a single font file, like an @font-face rule, which is only loaded once some text uses it
+pub(crate) struct FontFace {
+    pub(crate) family: String,
+    pub(crate) weight: u16,
+    pub(crate) style: FontStyle,
+    pub(crate) path: String,
+    handle: OnceLock<Handle<Font>>,
+}
+
+// every font which can be used by a font-family
+#[derive(Default)]
+pub(crate) struct Fonts {
+    faces: Vec<FontFace>,
+    asset_server: Option<AssetServer>, // to load faces with, from font_faces()
+}
+
+impl Fonts {
+    pub(crate) fn register(&mut self, family: &str, weight: u16, style: FontStyle, path: String) {
+        self.faces.push(FontFace { family: family.into(), weight, style, path, handle: OnceLock::new() });
+    }
+
+    pub(crate) fn is_loading(&self, asset_server: &AssetServer) -> bool {
+        self.faces.iter()
+            .filter_map(|face| face.handle.get())
+            .any(|handle| matches!(asset_server.load_state(handle), LoadState::Loading))
+    }
+
+    // starts loading the face the first time it's picked
+    fn handle(&self, face: &FontFace) -> Handle<Font> {
+        let Some(asset_server) = &self.asset_server else { return Handle::default(); };
+        face.handle.get_or_init(|| asset_server.load(face.path.clone())).clone()
+    }
+
+    // the first family in the list which has any faces wins, then the closest style and weight within it
+    pub(crate) fn resolve(&self, font: &FontProperties) -> Handle<Font> {
+        let weight = font.weight.unwrap_or(400);
+        let style = font.style.unwrap_or_default();
+
+        for family in font.family.as_deref().unwrap_or_default().split(',') {
+            let family = family.trim().trim_matches(|each| each == '"' || each == '\'');
+
+            let faces = self.faces.iter()
+                .filter(|face| face.family.eq_ignore_ascii_case(family))
+                .collect::<Vec<&FontFace>>();
+
+            // an italic face is better than a normal one of the right weight, but any face is better than none
+            let same_style = faces.iter().copied().filter(|face| face.style == style).collect::<Vec<&FontFace>>();
+            let candidates = if same_style.is_empty() { faces } else { same_style };
+
+            if let Some(face) = nearest_weight(&candidates, weight) {
+                return self.handle(face);
+            }
+        }
+
+        // FIXME generic families (sans-serif, monospace, etc.) and unknown families all use Bevy's built-in font
+        Handle::default()
+    }
+}
+
+// register a font for every @font-face rule in a stylesheet, `name` is only used for diagnostics
+pub(crate) fn font_faces(css: &mut CSS, asset_server: &AssetServer, name: &str, source: &str) {
+    css.fonts.asset_server = Some(asset_server.clone());
+
+    for rule in stylesheet::parse(source) {
+        let location = format!("{} {}", name, rule.prelude);
+        let diagnostic = |message: &str| Diagnostic { rule: &location, line: rule.line, message: message.into() };
+
+        if rule.prelude != "@font-face" {
+            warn!("{}", diagnostic("only @font-face rules are supported"));
+            continue;
+        }
+
+        // src isn't a property of elements, so handle it here and leave the rest to the declaration parser
+        let (src, declarations): (Vec<Declaration>, Vec<Declaration>) = declarations::parse(&rule.block).into_iter()
+            .map(|declaration| Declaration { line: rule.line + declaration.line - 1, ..declaration })
+            .partition(|declaration| declaration.property == "src");
+
+        let styles = declarations::styles(css, &location, &declarations);
+
+        let Some(path) = src.last().and_then(|src| url(&src.value)) else {
+            warn!("{}", diagnostic("@font-face requires a src: url(...)"));
+            continue;
+        };
+
+        let Some(family) = styles.font.family else {
+            warn!("{}", diagnostic("@font-face requires a font-family"));
+            continue;
+        };
+
+        let family = family.trim().trim_matches(|each| each == '"' || each == '\'');
+        css.fonts.register(family, styles.font.weight.unwrap_or(400), styles.font.style.unwrap_or_default(), path);
+    }
+}
+
+// the first url() in a src, e.g. url("fonts/FiraSans-Bold.ttf") format("truetype") => fonts/FiraSans-Bold.ttf
+// FIXME local() and fallback urls are ignored
+fn url(src: &str) -> Option<String> {
+    let start = src.find("url(")? + 4;
+    let end = start + src[start..].find(')')?;
+    Some(src[start..end].trim().trim_matches(|each| each == '"' || each == '\'').to_string())
+}
+
+// Fonts load in the background, and text which uses a font before it has loaded isn't laid out again by
+// itself, so mark that text as changed once its font arrives
+pub(crate) fn relayout(
+    css: Res<CSS>,
+    asset_server: Res<AssetServer>,
+    mut events: EventReader<AssetEvent<Font>>,
+    mut texts: Query<&mut Text>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    let loaded = events.read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } => Some(*id),
+            _ => None,
+        })
+        .collect::<HashSet<AssetId<Font>>>();
+
+    if !loaded.is_empty() {
+        for mut text in texts.iter_mut() {
+            if text.sections.iter().any(|section| loaded.contains(&section.style.font.id())) {
+                text.set_changed();
+            }
+        }
+    }
+
+    // until every font is in, see desktop_app() in main
+    if css.fonts.is_loading(&asset_server) {
+        redraw.send(RequestRedraw);
+    }
+}
+
+// https://www.w3.org/TR/css-fonts-4/#font-style-matching
+fn nearest_weight<'a>(faces: &[&'a FontFace], desired: u16) -> Option<&'a FontFace> {
+    let lighter = || faces.iter().copied().filter(|face| face.weight <= desired).max_by_key(|face| face.weight);
+    let heavier = || faces.iter().copied().filter(|face| face.weight >= desired).min_by_key(|face| face.weight);
+
+    match desired {
+        // 400 to 500 look up to 500 first, then down, then above 500
+        400..=500 => faces.iter().copied()
+            .filter(|face| face.weight >= desired && face.weight <= 500)
+            .min_by_key(|face| face.weight)
+            .or_else(lighter)
+            .or_else(heavier),
+        // lighter weights look down first, heavier weights look up first
+        _ if desired < 400 => lighter().or_else(heavier),
+        _ => heavier().or_else(lighter),
+    }
+}
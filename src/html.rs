@@ -1,39 +1,163 @@
+use bevy::a11y::accesskit::Role;
+use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
 
-use crate::{css, Link};
+use crate::{accessibility, css, Link};
+use crate::accessibility::{Alt, AriaLabel};
+use crate::computed::ComputedStyle;
 use crate::css::Cascading;
+use crate::focus::TabIndex;
+use crate::scroll::Scroll;
+use crate::text::InlineSections;
+use crate::transform::CssTransform;
+
+// the HTML element an entity was spawned as, e.g. "a" or "div"
+#[derive(Component)]
+pub(crate) struct Tag(pub &'static str);
+
+// id="...", which links to #fragments scroll to
+#[derive(Component)]
+pub(crate) struct Id(pub(crate) String);
 
 pub(crate) fn a(
     parent: &mut ChildBuilder,
     css: &css::CSS,
     classes: css::Classes,
     href: &str,
+    attributes: &[(&str, &str)],
     children: impl FnOnce(&mut ChildBuilder),
 ) {
-    parent.spawn((
+    let inline = inline_style(css, attributes);
+    let mut link = parent.spawn((
         ButtonBundle {
-            style: Style::cascade(css, &classes),
-            ..ButtonBundle::cascade(css, &classes)
+            style: Style::cascade(css, &Tag("a"), &classes, &inline),
+            ..ButtonBundle::cascade(css, &Tag("a"), &classes, &inline)
         },
         Link { href: href.into() },
-        classes
-    )).with_children(children);
+        classes,
+        inline,
+        ComputedStyle::default(),
+        CssTransform::default(),
+        Outline::default(), // for :focus-visible
+        Tag("a")
+    ));
+
+    html_attributes(&mut link, attributes);
+    link.with_children(children);
+}
+
+// the style attribute, which is cascaded along with the classes, e.g. [("style", "margin-left: 11px")]
+fn inline_style(css: &css::CSS, attributes: &[(&str, &str)]) -> css::InlineStyle {
+    css::InlineStyle::parse(css, style_attribute(attributes))
+}
+
+fn style_attribute<'a>(attributes: &[(&str, &'a str)]) -> &'a str {
+    attributes.iter().find(|(name, _)| *name == "style").map_or("", |(_, value)| *value)
+}
+
+// attributes other than class, href, etc. e.g. [("aria-label", "GitHub"), ("tabindex", "-1")]
+fn html_attributes(element: &mut EntityCommands, attributes: &[(&str, &str)]) {
+    for (name, value) in attributes {
+        match *name {
+            "style" => {} // see inline_style
+            "aria-label" => { element.insert(AriaLabel(value.to_string())); }
+            "id" => { element.insert(Id(value.to_string())); }
+            "tabindex" => match value.parse::<i32>() {
+                Ok(index) => { element.insert(TabIndex(index)); }
+                Err(_) => warn!("invalid value `{}` for attribute `tabindex`", value),
+            },
+            _ => warn!("unsupported attribute `{}`", name),
+        }
+    }
 }
 
-// used for: div, li, navbar, header, etc.
+// used for: div, li, etc.
 pub(crate) fn div(
     parent: &mut ChildBuilder,
     css: &css::CSS,
     classes: css::Classes,
+    attributes: &[(&str, &str)],
     children: impl FnOnce(&mut ChildBuilder),
 ) {
-    parent.spawn((
+    element(parent, css, "div", classes, attributes, children);
+}
+
+// a landmark, which screen readers can jump to
+pub(crate) fn header(
+    parent: &mut ChildBuilder,
+    css: &css::CSS,
+    classes: css::Classes,
+    attributes: &[(&str, &str)],
+    children: impl FnOnce(&mut ChildBuilder),
+) {
+    element(parent, css, "header", classes, attributes, children);
+}
+
+// a landmark, which screen readers can jump to
+pub(crate) fn main(
+    parent: &mut ChildBuilder,
+    css: &css::CSS,
+    classes: css::Classes,
+    attributes: &[(&str, &str)],
+    children: impl FnOnce(&mut ChildBuilder),
+) {
+    element(parent, css, "main", classes, attributes, children);
+}
+
+// a part of the page, usually with a heading and an id to link to
+pub(crate) fn section(
+    parent: &mut ChildBuilder,
+    css: &css::CSS,
+    classes: css::Classes,
+    attributes: &[(&str, &str)],
+    children: impl FnOnce(&mut ChildBuilder),
+) {
+    element(parent, css, "section", classes, attributes, children);
+}
+
+// a landmark, which screen readers can jump to
+pub(crate) fn nav(
+    parent: &mut ChildBuilder,
+    css: &css::CSS,
+    classes: css::Classes,
+    attributes: &[(&str, &str)],
+    children: impl FnOnce(&mut ChildBuilder),
+) {
+    element(parent, css, "nav", classes, attributes, children);
+}
+
+fn element(
+    parent: &mut ChildBuilder,
+    css: &css::CSS,
+    tag: &'static str,
+    classes: css::Classes,
+    attributes: &[(&str, &str)],
+    children: impl FnOnce(&mut ChildBuilder),
+) {
+    let inline = inline_style(css, attributes);
+    let mut element = parent.spawn((
         NodeBundle {
-            style: Style::cascade(css, &classes),
-            ..NodeBundle::cascade(css, &classes)
+            style: Style::cascade(css, &Tag(tag), &classes, &inline),
+            ..NodeBundle::cascade(css, &Tag(tag), &classes, &inline)
         },
-        classes
-    )).with_children(children);
+        classes,
+        inline,
+        ComputedStyle::default(),
+        CssTransform::default(),
+        Outline::default(), // for outline, and :focus-visible with a tabindex
+        Scroll::default(), // for overflow
+        Tag(tag)
+    ));
+
+    match tag {
+        "header" => { element.insert(accessibility::node(Role::Banner, "")); }
+        "main" => { element.insert(accessibility::node(Role::Main, "")); }
+        "nav" => { element.insert(accessibility::node(Role::Navigation, "")); }
+        _ => {}
+    }
+
+    html_attributes(&mut element, attributes);
+    element.with_children(children);
 }
 
 pub(crate) fn img(
@@ -41,33 +165,148 @@ pub(crate) fn img(
     css: &css::CSS,
     classes: css::Classes,
     image: Handle<Image>,
+    alt: &str,
+    attributes: &[(&str, &str)],
 ) {
-    parent.spawn(
-        // FIXME -- no alt text on images
+    let inline = inline_style(css, attributes);
+    let mut img = parent.spawn((
         ImageBundle {
-            style: Style::cascade(css, &classes),
+            style: Style::cascade(css, &Tag("img"), &classes, &inline),
             image: UiImage::new(image),
             ..default()
-        }
-    );
+        },
+        Alt(alt.into()),
+        classes,
+        inline,
+        ComputedStyle::default(),
+        CssTransform::default(),
+        Tag("img")
+    ));
+
+    // an empty alt is a decorative image, which screen readers skip
+    if !alt.is_empty() { img.insert(accessibility::node(Role::Image, alt)); }
+    html_attributes(&mut img, attributes);
 }
 
 pub(crate) fn text(
     parent: &mut ChildBuilder,
     css: &css::CSS,
     classes: css::Classes,
+    attributes: &[(&str, &str)],
     text: &str,
 ) {
-    let bundle = TextBundle::cascade(css, &classes);
-    parent.spawn((
+    inline_formatting_context(parent, css, "span", classes, attributes, vec![Inline::text(text)]);
+}
+
+// a paragraph of text which can contain <strong>, <em>, <code>, <a>, etc.
+pub(crate) fn p(
+    parent: &mut ChildBuilder,
+    css: &css::CSS,
+    classes: css::Classes,
+    attributes: &[(&str, &str)],
+    children: Vec<Inline>,
+) {
+    inline_formatting_context(parent, css, "p", classes, attributes, children);
+}
+
+// inline content, which is flattened into the sections of a single Text
+pub(crate) enum Inline {
+    Text(String),
+    Element {
+        tag: &'static str,
+        classes: css::Classes,
+        style: String,
+        href: Option<String>,
+        children: Vec<Inline>,
+    },
+}
+
+impl Inline {
+    pub(crate) fn text(text: &str) -> Self {
+        Inline::Text(text.into())
+    }
+
+    // <strong>, <em>, <code>, <span>, etc.
+    pub(crate) fn element(tag: &'static str, classes: css::Classes, attributes: &[(&str, &str)], children: Vec<Inline>) -> Self {
+        Inline::Element { tag, classes, style: inline_attributes(attributes), href: None, children }
+    }
+
+    pub(crate) fn a(classes: css::Classes, href: &str, attributes: &[(&str, &str)], children: Vec<Inline>) -> Self {
+        Inline::Element { tag: "a", classes, style: inline_attributes(attributes), href: Some(href.into()), children }
+    }
+}
+
+// inline elements have no Node for an id, tabindex, etc. to go on, so only style is supported
+fn inline_attributes(attributes: &[(&str, &str)]) -> String {
+    for (name, _) in attributes.iter().filter(|(name, _)| *name != "style") {
+        warn!("unsupported attribute `{}` on an inline element", name);
+    }
+    style_attribute(attributes).into()
+}
+
+// The element is a single TextBundle, and every inline element inside of it becomes a child entity without a
+// Node, which is styled like any other element, and whose ComputedStyle is copied onto its text sections by
+// text::sections
+fn inline_formatting_context(
+    parent: &mut ChildBuilder,
+    css: &css::CSS,
+    tag: &'static str,
+    classes: css::Classes,
+    attributes: &[(&str, &str)],
+    children: Vec<Inline>,
+) {
+    let inline = inline_style(css, attributes);
+    let bundle = TextBundle::cascade(css, &Tag(tag), &classes, &inline);
+
+    let mut sections = vec![];
+    let mut spans = vec![];
+
+    let mut element = parent.spawn((
         TextBundle {
-            style: Style::cascade(css, &classes),
-            text: Text::from_section(
-                text,
-                bundle.text.sections[0].clone().style // clunky to get style out of TextBundle
-            ),
+            style: Style::cascade(css, &Tag(tag), &classes, &inline),
             ..bundle
         },
-        classes
+        classes,
+        inline,
+        ComputedStyle::default(),
+        CssTransform::default(),
+        Tag(tag)
     ));
-}
\ No newline at end of file
+
+    html_attributes(&mut element, attributes);
+    element.with_children(|parent| flatten(parent, css, children, None, &mut sections, &mut spans));
+    let content = sections.iter().map(|section| section.value.clone()).collect();
+    element.insert((Text::from_sections(sections), InlineSections { spans, content }));
+}
+
+fn flatten(
+    parent: &mut ChildBuilder,
+    css: &css::CSS,
+    children: Vec<Inline>,
+    span: Option<Entity>,
+    sections: &mut Vec<TextSection>,
+    spans: &mut Vec<Option<Entity>>,
+) {
+    for child in children {
+        match child {
+            Inline::Text(value) => {
+                sections.push(TextSection::new(value, TextStyle::default()));
+                spans.push(span);
+            }
+            Inline::Element { tag, classes, style, href, children } => {
+                let inline = css::InlineStyle::parse(css, &style);
+                let mut element = parent.spawn((classes, inline, ComputedStyle::default(), Tag(tag)));
+
+                let entity = element.id();
+                let start = sections.len();
+                element.with_children(|parent| flatten(parent, css, children, Some(entity), sections, spans));
+
+                // hovered and pressed by text::inline_interaction, since Bevy only does that for nodes
+                if let Some(href) = href {
+                    let name = sections[start..].iter().map(|section| section.value.as_str()).collect::<String>();
+                    element.insert((accessibility::inline_link(name.trim(), &href), Link { href }, Interaction::default()));
+                }
+            }
+        }
+    }
+}
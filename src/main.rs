@@ -2,19 +2,36 @@ use bevy::asset::AssetMetaCheck;
 use bevy::prelude::*;
 use bevy::window::WindowResized;
 
+mod accessibility;
+mod animation;
+mod calc;
+mod color;
+mod computed;
 mod css;
+mod debug;
+mod declarations;
+mod effects;
+mod focus;
+mod fonts;
 mod html;
 mod patch;
+mod scroll;
+mod stylesheet;
+mod svg;
+mod text;
+mod transform;
+mod transition;
+
+#[cfg(test)]
+mod tests;
 
 // FIXME non-exhaustive list of missing features in Bevy
-//  - ImageBundle: no alt text on images, no native SVG support, cannot change brightness like when using CSS
-//  - TextStyle: font_size cannot be specified in rem (only px), has no font_weight property, no letter_spacing property
+//  - ImageBundle: no native SVG support (see svg.rs), no filter or opacity (see effects.rs)
+//  - TextStyle: font_size cannot be specified in rem (only px), no font_weight property (see fonts.rs), no letter_spacing or text decorations (see text.rs)
 //  - NodeBundle: has no block or none options for display (https://www.w3schools.com/css/css_display_visibility.asp)
-//  - UiBorderRadius: no border-radius property
 
 // FIXME in-progress Bevy Issues / PRs
-//  - to add SVG support: https://github.com/bevyengine/bevy/issues/1139
-//  - to add border-radius: https://github.com/bevyengine/bevy/pull/8973
+//  - to add SVG support, which would replace svg.rs: https://github.com/bevyengine/bevy/issues/1139
 
 #[derive(Component)]
 // <a href="...">
@@ -26,6 +43,18 @@ struct Link {
 // components which should be despawned before being redrawn
 struct Despawn;
 
+// the body of the page, a stand-in for the content of bevyengine.org
+const FEATURES: [(&str, &str); 8] = [
+    ("Data Driven", "All engine and game logic uses Bevy ECS, a custom Entity Component System. Fast, simple, and massively parallel."),
+    ("2D Renderer", "Render real-time 2D graphics for games and apps, with sprites, sprite sheets, text and meshes."),
+    ("3D Renderer", "A modern and flexible 3D renderer, with physically based lighting, shadows, cameras and meshes."),
+    ("Animation", "A powerful animation system, with skeletal animation driven by glTF files and blending between clips."),
+    ("Cross Platform", "Support for all major platforms: Windows, macOS, Linux, the Web, iOS and Android."),
+    ("Bevy UI", "A custom ECS-driven UI framework built specifically for Bevy, with flexbox and grid layout."),
+    ("Scenes", "Save ECS Worlds to human-readable scene files, and load scene files into ECS Worlds."),
+    ("Free and Open Source", "An engine built by and for the community. No fees. No strings attached. Free forever under the permissive MIT or Apache 2.0 licenses."),
+];
+
 #[derive(Resource, Default)]
 struct Images {
     bevy_logo_dark: Handle<Image>,
@@ -46,14 +75,46 @@ fn main() {
                  ..default()
              })
         )
+        // Only update on input, to save power. Anything which keeps changing without input (animations, fonts
+        // loading, smooth scrolling, nodes which are only laid out next frame) has to send a RequestRedraw for
+        // every frame it needs, until it's done.
         .insert_resource(bevy::winit::WinitSettings::desktop_app())
+        .add_plugins(page)
+        .run();
+}
+
+// everything but the window, so that tests.rs can lay the page out without one
+fn page(app: &mut App) {
+    app
+        .init_asset::<svg::Svg>()
+        .register_asset_loader(svg::SvgLoader)
         .insert_resource(Images::default())
         .insert_resource(ClearColor(Srgba::hex("#232326").unwrap().into()))
         .insert_resource(css::CSS::default())
+        .insert_resource(debug::DebugOverlay::default())
+        .insert_resource(focus::FocusVisible::default())
+        .insert_resource(focus::Location::initial())
+        .insert_resource(scroll::ReducedMotion::default())
+        .add_event::<focus::Navigate>()
         .add_systems(Startup, startup)
-        .add_systems(Update, (despawn, css::recalculate, render).chain())
-        .add_systems(Update, (css::a_hover, css::main_menu_link_hover, css::button_pink_hover))
-        .run();
+        .add_systems(Update, (despawn, css::recalculate, render, css::restyle).chain())
+        .add_systems(Update, (css::a_hover, css::hover.before(css::restyle), computed::inspect))
+        .add_systems(Update, (debug::toggle, debug::draw).chain())
+        .add_systems(Update, fonts::relayout)
+        .add_systems(Update, (focus::tab, focus::click.after(text::inline_interaction), focus::enter, focus::pseudo_classes.before(css::restyle)).chain())
+        .add_systems(Update, focus::navigate.after(focus::enter))
+        .add_systems(Update, (text::inline_interaction.before(css::hover), text::sections.after(css::restyle)))
+        .add_systems(Update, animation::animate.after(text::sections))
+        .add_systems(Update, (scroll::wheel, scroll::touch, scroll::keys, scroll::drag))
+        .add_systems(Update, (scroll::fragment.after(focus::navigate), scroll::smooth).chain())
+        .add_systems(PostUpdate, calc::evaluate.before(bevy::ui::UiSystem::Layout))
+        .add_systems(PostUpdate, svg::rasterize.after(bevy::ui::UiSystem::Layout))
+        .add_systems(PostUpdate, scroll::measure.after(bevy::ui::UiSystem::Layout).before(transform::apply))
+        .add_systems(PostUpdate, scroll::stick.after(scroll::measure).before(transform::apply))
+        .add_systems(PostUpdate, transform::apply.after(bevy::ui::UiSystem::Layout).before(bevy::transform::TransformSystem::TransformPropagate))
+        .add_systems(PostUpdate, scroll::spy.after(bevy::transform::TransformSystem::TransformPropagate))
+        .add_systems(PostUpdate, accessibility::links.before(bevy::a11y::AccessibilitySystem::Update))
+        .add_systems(PostUpdate, (text::adjust.after(bevy::ui::widget::text_system), text::decorate).chain());
 }
 
 fn startup(
@@ -65,18 +126,13 @@ fn startup(
 
     commands.spawn(Camera2dBundle::default());
 
-    // FIXME -- Bevy does not currently support SVG images natively
-    // This crate adds SVG support but hasn't been updated since Bevy v0.12.0: https://github.com/Weasy666/bevy_svg
-    // So instead, I converted all SVGs to PNGs
+    // SVGs are loaded by svg::SvgLoader, and rasterized again at whatever size their node ends up (see svg.rs)
     // load() them once in setup() so we don't need to load() every Update schedule
-    images.bevy_logo_dark = asset_server.load("bevy_logo_dark.png");
-    images.heart = asset_server.load("heart.png");
-
-    // FIXME cannot change brightness of images in Bevy like in CSS, so there is no hover effect on this
-    images.github_mark_white = asset_server.load("github-mark-white.png");
+    images.bevy_logo_dark = asset_server.load("bevy_logo_dark.svg");
+    images.heart = asset_server.load("heart.svg");
+    images.github_mark_white = asset_server.load("github-mark-white.svg");
 
-    // 500 font weight == Medium, according to https://fonts.google.com/specimen/Fira+Sans
-    css.fira_sans = asset_server.load("fonts/FiraSans-Medium.ttf");
+    fonts::font_faces(&mut css, &asset_server, "fonts.css", include_str!("fonts.css"));
 }
 
 fn render(
@@ -93,6 +149,7 @@ fn render(
             style: Style {
                 height: Val::Percent(100.),
                 width: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
                 ..default()
             },
             ..default()
@@ -101,40 +158,39 @@ fn render(
     )).with_children(|parent| {
 
         // layout__header
-        html::div(parent, &css, css::Classes(vec![css::Class::LayoutHeader]), |parent| {
+        html::header(parent, &css, css::Classes(vec![css::Class::LayoutHeader]), &[], |parent| {
 
             // header__content
-            html::div(parent, &css, css::Classes(vec![css::Class::HeaderContent]), |parent| {
+            html::div(parent, &css, css::Classes(vec![css::Class::HeaderContent]), &[], |parent| {
 
                 // <div> header__left-block
-                html::div(parent, &css, css::Classes(vec![css::Class::HeaderLeftBlock]), |parent| {
+                html::div(parent, &css, css::Classes(vec![css::Class::HeaderLeftBlock]), &[], |parent| {
 
                     // a.header__logo
-                    html::a(parent, &css, css::Classes(vec![css::Class::HeaderLogo]), "https://bevyengine.org/", |parent| {
+                    html::a(parent, &css, css::Classes(vec![css::Class::HeaderLogo]), "https://bevyengine.org/", &[], |parent| {
                         // <img> .logo
-                        html::img(parent, &css, css::Classes(vec![css::Class::Logo]), images.bevy_logo_dark.clone())
+                        html::img(parent, &css, css::Classes(vec![css::Class::Logo]), images.bevy_logo_dark.clone(), "Bevy Engine", &[])
                     });
 
                     // header__message
-                    // FIXME -- wonkiness here relative to bevyengine.org due to lack of letter-spacing CSS property
-                    html::text(parent, &css, css::Classes(vec![css::Class::HeaderMessage]), "Features");
+                    html::text(parent, &css, css::Classes(vec![css::Class::HeaderMessage]), &[("style", "margin-left: 11px")], "Features");
                 });
 
                 // <nav> header__menu main-menu
-                html::div(parent, &css, css::Classes(vec![]), |parent| {
+                html::nav(parent, &css, css::Classes(vec![]), &[], |parent| {
 
                     // <div> main-menu__content
-                    html::div(parent, &css, css::Classes(vec![]), |parent| {
+                    html::div(parent, &css, css::Classes(vec![]), &[], |parent| {
 
                         // <ul> main-menu__menu
-                        html::div(parent, &css, css::Classes(vec![]), |parent| {
+                        html::div(parent, &css, css::Classes(vec![]), &[], |parent| {
 
                             fn li(parent: &mut ChildBuilder, css: &css::CSS, text: &str, href: &str) {
                                 // <li> main-menu__entry
-                                html::div(parent, &css, css::Classes(vec![css::Class::MainMenuEntry]), |parent| {
+                                html::div(parent, &css, css::Classes(vec![css::Class::MainMenuEntry]), &[], |parent| {
                                     // <a> main-menu__link
-                                    html::a(parent, css, css::Classes(vec![css::Class::MainMenuLink]), href, |parent| {
-                                        html::text(parent, &css, css::Classes(vec![css::Class::MainMenuLinkText]), text);
+                                    html::a(parent, css, css::Classes(vec![css::Class::MainMenuLink]), href, &[], |parent| {
+                                        html::text(parent, &css, css::Classes(vec![]), &[], text);
                                     });
                                 });
                             }
@@ -150,22 +206,44 @@ fn render(
                 });
 
                 // <div> header__cta-container
-                html::div(parent, &css, css::Classes(vec![css::Class::HeaderCtaContainer]), |parent| {
+                html::div(parent, &css, css::Classes(vec![css::Class::HeaderCtaContainer]), &[], |parent| {
 
                     // <a> button--pink
-                    html::a(parent, &css, css::Classes(vec![css::Class::Button, css::Class::ButtonPink]), "/donate", |parent| {
-                        html::text(parent, &css, css::Classes(vec![css::Class::ButtonText]), "Donate");
-                        html::img(parent, &css, css::Classes(vec![css::Class::ButtonIcon]), images.heart.clone());
+                    html::a(parent, &css, css::Classes(vec![css::Class::Button, css::Class::ButtonPink]), "/donate", &[], |parent| {
+                        html::text(parent, &css, css::Classes(vec![]), &[], "Donate");
+                        html::img(parent, &css, css::Classes(vec![css::Class::ButtonIcon]), images.heart.clone(), "", &[]);
                     });
 
                     // <a> header__cta--github
-                    html::a(parent, &css, css::Classes(vec![css::Class::HeaderCtaGitHub]), "/donate", |parent| {
-                        html::img(parent, &css, css::Classes(vec![css::Class::HeaderCtaGitHubImg]), images.github_mark_white.clone());
+                    html::a(parent, &css, css::Classes(vec![css::Class::HeaderCtaGitHub]), "/donate", &[], |parent| {
+                        html::img(parent, &css, css::Classes(vec![css::Class::HeaderCtaGitHubImg]), images.github_mark_white.clone(), "GitHub", &[]);
                     })
 
                 })
             })
-        })
+        });
+
+        // layout__content, which scrolls under the header
+        // FIXME the page is spawned again on every resize, which scrolls it back to the top
+        html::main(parent, &css, css::Classes(vec![css::Class::LayoutContent]), &[], |parent| {
+            for (title, text) in FEATURES {
+                // e.g. #data-driven
+                let id = title.to_lowercase().replace(' ', "-");
+                html::section(parent, &css, css::Classes(vec![css::Class::Feature]), &[("id", &id)], |parent| {
+                    html::text(parent, &css, css::Classes(vec![css::Class::FeatureTitle]), &[], title);
+                    html::p(parent, &css, css::Classes(vec![css::Class::FeatureText]), &[], vec![html::Inline::text(text)]);
+                });
+            }
+
+            // <p class="feature__text">Read the <a href="...">Quick Start Guide</a> to <strong>get started</strong>.</p>
+            html::p(parent, &css, css::Classes(vec![css::Class::FeatureText]), &[], vec![
+                html::Inline::text("Read the "),
+                html::Inline::a(css::Classes(vec![]), "https://bevyengine.org/learn/quick-start/", &[], vec![html::Inline::text("Quick Start Guide")]),
+                html::Inline::text(" to "),
+                html::Inline::element("strong", css::Classes(vec![]), &[], vec![html::Inline::text("get started")]),
+                html::Inline::text("."),
+            ]);
+        });
     });
 }
 
@@ -181,10 +259,4 @@ fn despawn(
             commands.entity(e).despawn_recursive();
         }
     }
-}
-
-const DEBUG: bool = false;
-
-fn when_debugging<T: Default>(t: T) -> T {
-    if DEBUG { t } else { T::default() }
 }
\ No newline at end of file
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use bevy::prelude::*;
 use bevy::text::{BreakLineOn, TextLayoutInfo};
 
@@ -39,6 +41,47 @@ impl Patch for BorderColor {
     }
 }
 
+// each corner is patched separately, so that border-top-left-radius only overwrites the top left corner
+// the default radius is 0px, not auto like most other Vals
+impl Patch for BorderRadius {
+    fn apply(&self, patch: &Self) -> Self {
+        let corner = |target: Val, patch: Val| if patch == Val::ZERO { target } else { patch };
+
+        BorderRadius {
+            top_left: corner(self.top_left, patch.top_left),
+            top_right: corner(self.top_right, patch.top_right),
+            bottom_left: corner(self.bottom_left, patch.bottom_left),
+            bottom_right: corner(self.bottom_right, patch.bottom_right),
+        }
+    }
+}
+
+// each side is patched separately, so that an inline margin-left only overwrites the left margin
+// FIXME like BorderRadius, a side can't be patched back to 0px, since that's also the default
+impl Patch for UiRect {
+    fn apply(&self, patch: &Self) -> Self {
+        let side = |target: Val, patch: Val| if patch == Val::ZERO { target } else { patch };
+
+        UiRect {
+            left: side(self.left, patch.left),
+            right: side(self.right, patch.right),
+            top: side(self.top, patch.top),
+            bottom: side(self.bottom, patch.bottom),
+        }
+    }
+}
+
+// outline: none has to be able to override another rule, so the default (unset) width is auto, not 0
+impl Patch for Outline {
+    fn apply(&self, patch: &Self) -> Self {
+        Outline {
+            width: patch_single_field(&self.width, &patch.width),
+            offset: patch_single_field(&self.offset, &patch.offset),
+            color: patch_single_field(&self.color, &patch.color),
+        }
+    }
+}
+
 impl Patch for ButtonBundle {
     fn apply(&self, patch: &Self) -> Self {
         ButtonBundle {
@@ -48,6 +91,7 @@ impl Patch for ButtonBundle {
             interaction: patch_single_field(&self.interaction, &patch.interaction),
             focus_policy: patch_single_field(&self.focus_policy, &patch.focus_policy),
             border_color: self.border_color.apply(&patch.border_color),
+            border_radius: self.border_radius.apply(&patch.border_radius),
             image: self.image.apply(&patch.image),
             transform: patch_single_field(&self.transform, &patch.transform),
             global_transform: patch_single_field(&self.global_transform, &patch.global_transform),
@@ -87,6 +131,7 @@ impl Patch for NodeBundle {
             style: patch_single_field(&self.style, &patch.style),
             background_color: self.background_color.apply(&patch.background_color),
             border_color: self.border_color.apply(&patch.border_color),
+            border_radius: self.border_radius.apply(&patch.border_radius),
             focus_policy: patch_single_field(&self.focus_policy, &patch.focus_policy),
             transform: patch_single_field(&self.transform, &patch.transform),
             global_transform: patch_single_field(&self.global_transform, &patch.global_transform),
@@ -123,9 +168,9 @@ impl Patch for Style {
             justify_self: patch_single_field(&self.justify_self, &patch.justify_self),
             align_content: patch_single_field(&self.align_content, &patch.align_content),
             justify_content: patch_single_field(&self.justify_content, &patch.justify_content),
-            margin: patch_single_field(&self.margin, &patch.margin),
-            padding: patch_single_field(&self.padding, &patch.padding),
-            border: patch_single_field(&self.border, &patch.border),
+            margin: self.margin.apply(&patch.margin),
+            padding: self.padding.apply(&patch.padding),
+            border: self.border.apply(&patch.border),
             flex_direction: patch_single_field(&self.flex_direction, &patch.flex_direction),
             flex_wrap: patch_single_field(&self.flex_wrap, &patch.flex_wrap),
             flex_grow: patch_single_field(&self.flex_grow, &patch.flex_grow),
@@ -150,7 +195,11 @@ impl Patch for Style {
 impl Patch for Text {
     fn apply(&self, patch: &Self) -> Self {
         Text {
-            sections: self.sections.apply(&patch.sections),
+            sections: match (self.sections.as_slice(), patch.sections.as_slice()) {
+                // cascaded Text carries a single, empty section which holds the TextStyle, so merge those
+                ([section], [patch_section]) => vec![section.apply(patch_section)],
+                _ => self.sections.apply(&patch.sections),
+            },
             justify: patch_single_field(&self.justify, &patch.justify),
             // BreakLineOn does not implement Default, but the default value of linebreak_behavior
             // in Text is BreakLineOn::WordBoundary
@@ -181,6 +230,25 @@ impl Patch for TextBundle {
     }
 }
 
+impl Patch for TextSection {
+    fn apply(&self, patch: &Self) -> Self {
+        TextSection {
+            value: patch_single_field(&self.value, &patch.value),
+            style: self.style.apply(&patch.style),
+        }
+    }
+}
+
+impl Patch for TextStyle {
+    fn apply(&self, patch: &Self) -> Self {
+        TextStyle {
+            font: patch_single_field(&self.font, &patch.font),
+            font_size: patch_single_field(&self.font_size, &patch.font_size),
+            color: patch_single_field(&self.color, &patch.color),
+        }
+    }
+}
+
 impl Patch for TextLayoutInfo {
     fn apply(&self, patch: &Self) -> Self {
         TextLayoutInfo {
@@ -209,6 +277,14 @@ impl<T: Clone> Patch for Vec<T> {
     }
 }
 
+// maps are merged key-wise, keys in the patch overwrite the same keys in self
+impl<K: Ord + Clone, V: Clone> Patch for BTreeMap<K, V> {
+    fn apply(&self, patch: &Self) -> Self {
+        let mut merged = self.clone();
+        merged.extend(patch.iter().map(|(key, value)| (key.clone(), value.clone())));
+        merged
+    }
+}
 
 // FIXME once https://github.com/bevyengine/bevy/pull/12532 is merged
 //   remove this and use patch_single_field instead
@@ -223,4 +299,18 @@ impl Patch for ZIndex {
             *patch
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn margin_left_keeps_the_other_sides() {
+        // margin: 8px auto; in a class, and style="margin-left: 11px" on the element
+        let class = Style { margin: UiRect::axes(Val::Auto, Val::Px(8.)), ..default() };
+        let inline = Style { margin: UiRect::left(Val::Px(11.)), ..default() };
+
+        let margin = class.apply(&inline).margin;
+        assert_eq!(margin, UiRect { left: Val::Px(11.), right: Val::Auto, top: Val::Px(8.), bottom: Val::Px(8.) });
+    }
+}
@@ -0,0 +1,612 @@
+use bevy::a11y::Focus;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::window::{PrimaryWindow, RequestRedraw};
+
+use crate::computed::ComputedStyle;
+use crate::focus::Location;
+use crate::html::Id;
+use crate::patch::Patch;
+use crate::transform::{CssTransform, TransformFunction, TransformProperties};
+use crate::transition::CubicBezier;
+
+// how far a line of the mouse wheel or an arrow key scrolls, in px
+const LINE: f32 = 40.;
+
+// how long scroll-behavior: smooth takes, in seconds, which browsers each pick for themselves
+const SMOOTH: f32 = 0.4;
+
+// how many frames fragment() waits for the page to be spawned and laid out before it gives up
+const FRAGMENT_FRAMES: u32 = 60;
+
+// overflow-x and overflow-y, Bevy only knows whether to clip, see Style::overflow
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Overflow {
+    #[default]
+    Visible,
+    Hidden, // clipped, and only scrolled by the page itself, e.g. to a #fragment
+    Clip,
+    Auto,
+    Scroll, // like auto, but always shows the scrollbar
+}
+
+impl Overflow {
+    // whether the wheel, touch and keys scroll it
+    fn is_user_scrollable(&self) -> bool {
+        matches!(self, Overflow::Auto | Overflow::Scroll)
+    }
+}
+
+// Bevy lays out fixed elements as absolute and sticky elements as relative, and they're moved on from there
+// as things scroll by transform::apply and stick()
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Position {
+    #[default]
+    Relative,
+    Absolute,
+    Fixed,
+    Sticky,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum ScrollBehavior {
+    #[default]
+    Auto,
+    Smooth,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum ScrollbarWidth {
+    #[default]
+    Auto,
+    Thin,
+    None,
+}
+
+// overflow-x, overflow-y, position, scroll-behavior, scroll-margin-top, scrollbar-color and scrollbar-width,
+// None means "not declared"
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ScrollProperties {
+    pub(crate) position: Option<Position>,
+    pub(crate) overflow_x: Option<Overflow>,
+    pub(crate) overflow_y: Option<Overflow>,
+    pub(crate) behavior: Option<ScrollBehavior>,
+    pub(crate) margin_top: Option<Val>,
+    pub(crate) scrollbar_color: Option<(Color, Color)>, // thumb, track
+    pub(crate) scrollbar_width: Option<ScrollbarWidth>,
+}
+
+impl Patch for ScrollProperties {
+    fn apply(&self, patch: &Self) -> Self {
+        ScrollProperties {
+            position: patch.position.or(self.position),
+            overflow_x: patch.overflow_x.or(self.overflow_x),
+            overflow_y: patch.overflow_y.or(self.overflow_y),
+            behavior: patch.behavior.or(self.behavior),
+            margin_top: patch.margin_top.or(self.margin_top),
+            scrollbar_color: patch.scrollbar_color.or(self.scrollbar_color),
+            scrollbar_width: patch.scrollbar_width.or(self.scrollbar_width),
+        }
+    }
+}
+
+impl ScrollProperties {
+    pub(crate) fn position(&self) -> Position {
+        self.position.unwrap_or_default()
+    }
+
+    fn overflow(&self) -> (Overflow, Overflow) {
+        (self.overflow_x.unwrap_or_default(), self.overflow_y.unwrap_or_default())
+    }
+
+    // whether it clips its contents, and so is what sticky elements inside of it stick to
+    fn is_scroll_container(&self) -> bool {
+        self.overflow() != (Overflow::Visible, Overflow::Visible)
+    }
+}
+
+// How far an element's contents are scrolled, on every element which could scroll (see html::element), applied
+// to its children by transform::apply. `max` is measured after every layout
+#[derive(Component, Default)]
+pub(crate) struct Scroll {
+    pub(crate) offset: Vec2,
+    pub(crate) max: Vec2,
+}
+
+impl Scroll {
+    // scroll to an offset, as far as it goes, and tell whether it moved
+    pub(crate) fn scroll_to(&mut self, offset: Vec2) -> bool {
+        let offset = offset.clamp(Vec2::ZERO, self.max);
+        let moved = offset != self.offset;
+        self.offset = offset;
+        moved
+    }
+}
+
+// an element being scrolled smoothly by fragment(), until it gets there or something else scrolls it
+#[derive(Component)]
+pub(crate) struct SmoothScroll {
+    from: Vec2,
+    to: Vec2,
+    start: f32, // Time::elapsed_seconds
+    last: Vec2, // where smooth() last scrolled it to
+}
+
+// prefers-reduced-motion, which makes smooth scrolling jump straight there instead
+// FIXME nothing asks the OS or the browser yet, so it's only ever set by hand
+#[derive(Resource, Default)]
+pub(crate) struct ReducedMotion(pub(crate) bool);
+
+// the track of an element's vertical scrollbar, a child of the element which is drawn on top of its contents
+// rather than scrolled with them
+#[derive(Component)]
+pub(crate) struct Scrollbar;
+
+// the part of the scrollbar which can be dragged
+#[derive(Component)]
+pub(crate) struct Thumb {
+    container: Entity,
+}
+
+// the elements under a point which the user can scroll, innermost first, so that whatever can't scroll any
+// further passes the rest on to its ancestors
+fn under(
+    point: Vec2,
+    containers: &Query<(Entity, &Node, &GlobalTransform, &ComputedStyle, &mut Scroll)>,
+    parents: &Query<&Parent>,
+) -> Vec<Entity> {
+    let mut under = containers.iter()
+        .filter(|(_, node, transform, ..)| node.logical_rect(transform).contains(point))
+        .map(|(entity, ..)| (entity, parents.iter_ancestors(entity).count()))
+        .collect::<Vec<(Entity, usize)>>();
+
+    under.sort_by_key(|(_, depth)| std::cmp::Reverse(*depth));
+    under.into_iter().map(|(entity, _)| entity).collect()
+}
+
+// scroll the first of `candidates` which can move by `delta` at all
+fn scroll_by(
+    candidates: impl IntoIterator<Item = Entity>,
+    delta: Vec2,
+    containers: &mut Query<(Entity, &Node, &GlobalTransform, &ComputedStyle, &mut Scroll)>,
+) {
+    for entity in candidates {
+        let Ok((.., computed, mut scroll)) = containers.get_mut(entity) else { continue; };
+
+        let (x, y) = computed.scroll.overflow();
+        let delta = Vec2::new(
+            if x.is_user_scrollable() { delta.x } else { 0. },
+            if y.is_user_scrollable() { delta.y } else { 0. },
+        );
+
+        let offset = scroll.offset + delta;
+        if scroll.scroll_to(offset) { return; }
+    }
+}
+
+// the mouse wheel and trackpads scroll whatever is under the cursor, Shift+wheel scrolls sideways
+pub(crate) fn wheel(
+    mut wheel: EventReader<MouseWheel>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    parents: Query<&Parent>,
+    mut containers: Query<(Entity, &Node, &GlobalTransform, &ComputedStyle, &mut Scroll)>,
+) {
+    let Some(cursor) = windows.get_single().ok().and_then(|window| window.cursor_position()) else { return; };
+
+    for event in wheel.read() {
+        let mut delta = match event.unit {
+            MouseScrollUnit::Line => Vec2::new(event.x, event.y) * LINE,
+            MouseScrollUnit::Pixel => Vec2::new(event.x, event.y),
+        };
+        if keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) { delta = Vec2::new(delta.y, delta.x); }
+
+        // scrolling the wheel up (a positive y) moves the contents down
+        let candidates = under(cursor, &containers, &parents);
+        scroll_by(candidates, -delta, &mut containers);
+    }
+}
+
+// dragging a finger moves the contents along with it, e.g. on a phone
+pub(crate) fn touch(
+    touches: Res<Touches>,
+    parents: Query<&Parent>,
+    mut containers: Query<(Entity, &Node, &GlobalTransform, &ComputedStyle, &mut Scroll)>,
+) {
+    for touch in touches.iter() {
+        if touch.delta() == Vec2::ZERO { continue; }
+
+        let candidates = under(touch.start_position(), &containers, &parents);
+        scroll_by(candidates, -touch.delta(), &mut containers);
+    }
+}
+
+// the arrow keys, PageUp, PageDown, Home and End scroll whatever contains the focus, or else whatever is under
+// the cursor, or else the outermost element which scrolls, like the page in a browser
+pub(crate) fn keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    focus: Res<Focus>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    parents: Query<&Parent>,
+    mut containers: Query<(Entity, &Node, &GlobalTransform, &ComputedStyle, &mut Scroll)>,
+) {
+    let keys = [KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ArrowLeft, KeyCode::ArrowRight, KeyCode::PageUp, KeyCode::PageDown, KeyCode::Home, KeyCode::End];
+    let Some(key) = keys.into_iter().find(|key| keyboard.just_pressed(*key)) else { return; };
+
+    let scrolls = |entity: &Entity| containers.get(*entity).is_ok_and(|(.., computed, _)| {
+        let (x, y) = computed.scroll.overflow();
+        x.is_user_scrollable() || y.is_user_scrollable()
+    });
+
+    let focused = focus.0.into_iter().flat_map(|focused| parents.iter_ancestors(focused)).filter(scrolls).collect::<Vec<Entity>>();
+    let cursor = windows.get_single().ok().and_then(|window| window.cursor_position());
+    let hovered = cursor.map(|cursor| under(cursor, &containers, &parents)).unwrap_or_default().into_iter().filter(scrolls).collect::<Vec<Entity>>();
+    let outermost = containers.iter()
+        .map(|(entity, ..)| entity)
+        .filter(scrolls)
+        .min_by_key(|entity| parents.iter_ancestors(*entity).count());
+
+    let candidates = if !focused.is_empty() { focused } else if !hovered.is_empty() { hovered } else { outermost.into_iter().collect() };
+    let Some(first) = candidates.first().and_then(|entity| containers.get(*entity).ok()) else { return; };
+    let (_, node, _, _, scroll) = first;
+
+    // a page is a little less than the height of the element, so that the last line stays in view
+    let page = (node.size().y - 2. * LINE).max(LINE);
+    let delta = match key {
+        KeyCode::ArrowUp => Vec2::new(0., -LINE),
+        KeyCode::ArrowDown => Vec2::new(0., LINE),
+        KeyCode::ArrowLeft => Vec2::new(-LINE, 0.),
+        KeyCode::ArrowRight => Vec2::new(LINE, 0.),
+        KeyCode::PageUp => Vec2::new(0., -page),
+        KeyCode::PageDown => Vec2::new(0., page),
+        KeyCode::Home => Vec2::new(0., -scroll.offset.y),
+        _ => Vec2::new(0., scroll.max.y - scroll.offset.y),
+    };
+
+    scroll_by(candidates, delta, &mut containers);
+}
+
+// dragging the thumb scrolls by as much as it moves, relative to the whole contents
+pub(crate) fn drag(
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    thumbs: Query<(&Thumb, &Interaction)>,
+    mut containers: Query<(&Node, &mut Scroll)>,
+    mut dragging: Local<Option<(Entity, f32, f32)>>, // container, cursor y and scroll offset when the drag started
+) {
+    let Some(cursor) = windows.get_single().ok().and_then(|window| window.cursor_position()) else { return; };
+
+    if mouse.just_pressed(MouseButton::Left) {
+        *dragging = thumbs.iter()
+            .find(|(_, interaction)| **interaction == Interaction::Pressed)
+            .and_then(|(thumb, _)| containers.get(thumb.container).ok().map(|(_, scroll)| (thumb.container, cursor.y, scroll.offset.y)));
+    }
+    if !mouse.pressed(MouseButton::Left) {
+        *dragging = None;
+    }
+
+    let Some((container, start, offset)) = *dragging else { return; };
+    let Ok((node, mut scroll)) = containers.get_mut(container) else { return; };
+
+    let visible = node.size().y;
+    if visible <= 0. { return; }
+
+    let offset = Vec2::new(scroll.offset.x, offset + (cursor.y - start) * (visible + scroll.max.y) / visible);
+    scroll.scroll_to(offset);
+}
+
+// Measure how far each element can scroll once it's been laid out, and keep its scrollbar in line with it.
+// Bevy has no scroll containers, so the contents are laid out at full size and clipped (see Style::overflow)
+// FIXME only the element's children count towards its contents, not whatever overflows them in turn
+pub(crate) fn measure(
+    mut commands: Commands,
+    mut containers: Query<(Entity, &Node, &ComputedStyle, &mut Scroll, Option<&Children>)>,
+    mut children: Query<(&Node, &mut CssTransform, &Transform, Option<&ComputedStyle>), (Without<Scrollbar>, Without<Thumb>)>,
+    scrollbars: Query<(Entity, &Children), With<Scrollbar>>,
+    mut thumbs: Query<(&mut Style, &mut BackgroundColor, &mut CssTransform), (With<Thumb>, Without<Scrollbar>)>,
+    mut tracks: Query<(&mut Style, &mut BackgroundColor), (With<Scrollbar>, Without<Thumb>)>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    for (entity, node, computed, mut scroll, entity_children) in containers.iter_mut() {
+        let (overflow_x, overflow_y) = computed.scroll.overflow();
+        if overflow_x == Overflow::Visible && overflow_y == Overflow::Visible && scroll.max == Vec2::ZERO { continue; }
+
+        let size = node.size();
+        let mut content = size;
+
+        // children are laid out around the center of the element, and its bottom and right padding scroll too
+        let padding = Vec2::new(px(computed.style.padding.right), px(computed.style.padding.bottom));
+        let mut scrollbar = None;
+        for child in entity_children.into_iter().flatten() {
+            if let Ok((child, mut css_transform, transform, child_computed)) = children.get_mut(*child) {
+                // fixed elements stay where they are, so there's no scrolling to them
+                if child_computed.is_some_and(|computed| computed.scroll.position() == Position::Fixed) { continue; }
+
+                let layout = css_transform.layout(transform).truncate();
+                content = content.max(layout + child.size() / 2. + size / 2. + padding);
+            } else if let Ok((track, track_children)) = scrollbars.get(*child) {
+                scrollbar = Some((track, track_children.first().copied()));
+            }
+        }
+
+        // visible contents can't be scrolled at all, and clip only clips
+        let max = (content - size).max(Vec2::ZERO);
+        scroll.max = Vec2::new(
+            if matches!(overflow_x, Overflow::Visible | Overflow::Clip) { 0. } else { max.x },
+            if matches!(overflow_y, Overflow::Visible | Overflow::Clip) { 0. } else { max.y },
+        );
+
+        let offset = scroll.offset;
+        if scroll.scroll_to(offset) { redraw.send(RequestRedraw); }
+
+        // FIXME no horizontal scrollbar
+        let width = match computed.scroll.scrollbar_width.unwrap_or_default() {
+            ScrollbarWidth::Auto => 12.,
+            ScrollbarWidth::Thin => 8.,
+            ScrollbarWidth::None => 0.,
+        };
+        let shows = width > 0. && (overflow_y == Overflow::Scroll || (overflow_y == Overflow::Auto && scroll.max.y > 0.));
+
+        match scrollbar {
+            None if shows => {
+                let thumb = commands.spawn((NodeBundle::default(), Thumb { container: entity }, Interaction::default(), CssTransform::default())).id();
+                let track = commands.spawn((
+                    NodeBundle {
+                        style: Style { position_type: PositionType::Absolute, top: Val::Px(0.), right: Val::Px(0.), height: Val::Percent(100.), ..default() },
+                        ..default()
+                    },
+                    Scrollbar,
+                )).add_child(thumb).id();
+                commands.entity(entity).add_child(track);
+                redraw.send(RequestRedraw);
+            }
+            Some((track, _)) if !shows => {
+                commands.entity(track).despawn_recursive();
+                redraw.send(RequestRedraw);
+            }
+            Some((track, Some(thumb))) => {
+                // browsers default to something gray, and `auto` colors
+                let (thumb_color, track_color) = computed.scroll.scrollbar_color.unwrap_or((Color::srgba(0.5, 0.5, 0.5, 0.8), Color::NONE));
+                let track_height = size.y;
+                let thumb_height = (size.y / (size.y + scroll.max.y) * track_height).clamp(width * 2., track_height);
+                let top = if scroll.max.y > 0. { scroll.offset.y / scroll.max.y * (track_height - thumb_height) } else { 0. };
+
+                if let Ok((mut style, mut background_color)) = tracks.get_mut(track) {
+                    if style.width != Val::Px(width) { style.width = Val::Px(width); redraw.send(RequestRedraw); }
+                    background_color.set_if_neq(computed.with_opacity(track_color).into());
+                }
+
+                if let Ok((mut style, mut background_color, mut css_transform)) = thumbs.get_mut(thumb) {
+                    if style.height != Val::Px(thumb_height) {
+                        *style = Style { width: Val::Percent(100.), height: Val::Px(thumb_height), ..default() };
+                        redraw.send(RequestRedraw);
+                    }
+                    background_color.set_if_neq(computed.with_opacity(thumb_color).into());
+                    css_transform.properties = TransformProperties {
+                        transform: Some(vec![TransformFunction::Translate(Val::Px(0.), Val::Px(top))]),
+                        origin: None,
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Scroll to the element whose id is the location's #fragment, so that its top is at the top of whatever it
+// scrolls in, less its scroll-margin-top, e.g. just below a fixed header. It waits for the element to be spawned
+// and laid out, for links to other pages and deep links when the page starts, but not for ids which aren't there.
+// FIXME only the nearest element which scrolls is scrolled, not any which contain it
+pub(crate) fn fragment(
+    mut commands: Commands,
+    location: Res<Location>,
+    mut pending: Local<bool>,
+    mut waited: Local<u32>,
+    reduced_motion: Res<ReducedMotion>,
+    time: Res<Time>,
+    elements: Query<(Entity, &Id, &Node, &GlobalTransform, &ComputedStyle)>,
+    parents: Query<&Parent>,
+    mut containers: Query<(&Node, &GlobalTransform, &ComputedStyle, &mut Scroll)>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    if location.is_changed() { (*pending, *waited) = (location.fragment.is_some(), 0); }
+    if !*pending { return; }
+
+    let Some(fragment) = &location.fragment else { return; };
+    let found = elements.iter().find(|(_, id, ..)| id.0 == *fragment);
+
+    // the page might not have been spawned or laid out yet, but once it has there's nothing to wait for
+    let is_spawned = !elements.is_empty();
+    let is_laid_out = found.is_some_and(|(_, _, node, ..)| node.size() != Vec2::ZERO);
+    if !is_laid_out {
+        *waited += 1;
+        if (found.is_some() || !is_spawned) && *waited < FRAGMENT_FRAMES {
+            redraw.send(RequestRedraw);
+        } else {
+            warn!("can't scroll to #{}, no element with that id was laid out", fragment);
+            *pending = false;
+        }
+        return;
+    }
+    *pending = false;
+
+    let (entity, _, node, transform, computed) = found.expect("laid out above");
+
+    let container = parents.iter_ancestors(entity).find(|ancestor| {
+        containers.get(*ancestor).is_ok_and(|(_, _, computed, _)| computed.scroll.overflow().1 != Overflow::Visible)
+    });
+    let Some((container_node, container_transform, container_computed, mut scroll)) = container.and_then(|container| containers.get_mut(container).ok()) else {
+        return;
+    };
+
+    let top = scroll_top(node, transform, computed, container_node, container_transform, &scroll);
+    let to = Vec2::new(scroll.offset.x, top).clamp(Vec2::ZERO, scroll.max);
+
+    let smooth = container_computed.scroll.behavior.unwrap_or_default() == ScrollBehavior::Smooth;
+    if smooth && !reduced_motion.0 {
+        let from = scroll.offset;
+        commands.entity(container.expect("found above")).insert(SmoothScroll { from, to, start: time.elapsed_seconds(), last: from });
+    } else {
+        scroll.scroll_to(to);
+    }
+    redraw.send(RequestRedraw);
+}
+
+// how far an element's scroll container has to be scrolled for the element to be at its top, less its
+// scroll-margin-top
+fn scroll_top(
+    node: &Node,
+    transform: &GlobalTransform,
+    computed: &ComputedStyle,
+    container_node: &Node,
+    container_transform: &GlobalTransform,
+    scroll: &Scroll,
+) -> f32 {
+    let margin = match computed.scroll.margin_top.unwrap_or(Val::ZERO) {
+        Val::Px(px) => px,
+        Val::Percent(percent) => container_node.size().y * percent / 100.,
+        // FIXME viewport units
+        _ => 0.,
+    };
+    node.logical_rect(transform).min.y - container_node.logical_rect(container_transform).min.y + scroll.offset.y - margin
+}
+
+// Keep the location's #fragment on the last element with an id which has been scrolled to, so that it's whatever
+// is being read rather than the last link which was followed. It bypasses change detection, so that fragment()
+// doesn't scroll there again, and leaves the fragment alone while fragment() is scrolling to it.
+// FIXME a short last section can't be scrolled to the top, so the one before it wins at the end of the page
+pub(crate) fn spy(
+    mut location: ResMut<Location>,
+    elements: Query<(Entity, &Id, &Node, &GlobalTransform, &ComputedStyle)>,
+    parents: Query<&Parent>,
+    containers: Query<(Entity, &Node, &GlobalTransform, &ComputedStyle, &Scroll, Has<SmoothScroll>)>,
+    mut offsets: Local<HashMap<Entity, Vec2>>,
+) {
+    // only when something was scrolled by the wheel, keys, etc. since last time
+    let previous = std::mem::take(&mut *offsets);
+    let mut is_scrolled = false;
+    let mut is_navigating = location.is_changed();
+    for (entity, .., scroll, smooth) in containers.iter() {
+        is_scrolled |= previous.get(&entity).is_some_and(|offset| *offset != scroll.offset);
+        is_navigating |= smooth;
+        offsets.insert(entity, scroll.offset);
+    }
+    if !is_scrolled || is_navigating { return; }
+
+    let mut current: Option<(f32, &Id)> = None;
+    for (entity, id, node, transform, computed) in elements.iter() {
+        let container = parents.iter_ancestors(entity).find_map(|ancestor| {
+            containers.get(ancestor).ok().filter(|(_, _, _, computed, ..)| computed.scroll.overflow().1 != Overflow::Visible)
+        });
+        let Some((_, container_node, container_transform, _, scroll, _)) = container else { continue; };
+
+        // with a pixel to spare, for wherever fragment() left it
+        let top = scroll_top(node, transform, computed, container_node, container_transform, scroll);
+        if top > scroll.offset.y + 1. { continue; }
+        if current.map_or(true, |(best, _)| top > best) { current = Some((top, id)); }
+    }
+
+    let fragment = current.map(|(_, id)| id.0.clone());
+    if location.fragment != fragment { location.bypass_change_detection().fragment = fragment; }
+}
+
+// ease elements towards where fragment() is scrolling them
+pub(crate) fn smooth(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut containers: Query<(Entity, &mut Scroll, &mut SmoothScroll)>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    for (entity, mut scroll, mut smooth) in containers.iter_mut() {
+        // the wheel, keys, etc. take over
+        if scroll.offset != smooth.last {
+            commands.entity(entity).remove::<SmoothScroll>();
+            continue;
+        }
+
+        let t = ((time.elapsed_seconds() - smooth.start) / SMOOTH).min(1.);
+        scroll.scroll_to(smooth.from.lerp(smooth.to, CubicBezier::EASE_IN_OUT.ease(t)));
+        smooth.last = scroll.offset;
+
+        if t >= 1. { commands.entity(entity).remove::<SmoothScroll>(); }
+        redraw.send(RequestRedraw);
+    }
+}
+
+// Keep sticky elements in view of the element they scroll with, inset by their top or bottom, but only for as
+// long as their parent is in view too, e.g. section headers which give way to the next section's.
+// FIXME no left or right, and transforms and scrolling in between the element and its scroll container are ignored
+pub(crate) fn stick(
+    elements: Query<(Entity, &ComputedStyle), With<CssTransform>>,
+    parents: Query<&Parent>,
+    containers: Query<(&Node, &ComputedStyle, &Scroll)>,
+    sizes: Query<&Node>,
+    mut nodes: Query<(&mut CssTransform, &Transform)>,
+) {
+    for (entity, computed) in elements.iter() {
+        let container = parents.iter_ancestors(entity).find(|ancestor| {
+            containers.get(*ancestor).is_ok_and(|(_, computed, _)| computed.scroll.is_scroll_container())
+        });
+
+        let shift = match (computed.scroll.position(), container, parents.get(entity)) {
+            (Position::Sticky, Some(container), Ok(parent)) => {
+                let (container_node, _, scroll) = containers.get(container).expect("found above");
+
+                // where the element and its parent are within the contents of the container, before scrolling
+                let mut top_left = |entity: Entity| -> Option<Vec2> {
+                    let mut top_left = Vec2::ZERO;
+                    let mut each = entity;
+                    while each != container {
+                        let parent = parents.get(each).ok()?.get();
+                        let (mut css_transform, transform) = nodes.get_mut(each).ok()?;
+                        let (size, parent_size) = (sizes.get(each).ok()?.size(), sizes.get(parent).ok()?.size());
+
+                        // layout is around the center of the parent
+                        top_left += css_transform.layout(transform).truncate() + parent_size / 2. - size / 2.;
+                        each = parent;
+                    }
+                    Some(top_left)
+                };
+
+                let (Some(top), Some(parent_top)) = (top_left(entity), top_left(parent.get())) else { continue; };
+                let size = sizes.get(entity).map_or(Vec2::ZERO, Node::size);
+                let parent_size = if parent.get() == container {
+                    container_node.size() + scroll.max
+                } else {
+                    sizes.get(parent.get()).map_or(Vec2::ZERO, Node::size)
+                };
+
+                let (top, bottom, parent_top, parent_bottom) = (top.y, top.y + size.y, parent_top.y, parent_top.y + parent_size.y);
+                let visible = container_node.size().y;
+                let inset = |val: Val| match val {
+                    Val::Px(px) => Some(px),
+                    Val::Percent(percent) => Some(visible * percent / 100.),
+                    Val::Auto => None,
+                    // FIXME viewport units
+                    _ => Some(0.),
+                };
+
+                // moving down as far as the bottom of the parent, or up as far as its top
+                match (inset(computed.style.top), inset(computed.style.bottom)) {
+                    (Some(inset), _) if scroll.offset.y + inset > top => (scroll.offset.y + inset - top).min(parent_bottom - bottom).max(0.),
+                    (_, Some(inset)) if scroll.offset.y + visible - inset < bottom => -(bottom - scroll.offset.y - visible + inset).min(top - parent_top).max(0.),
+                    _ => 0.,
+                }
+            }
+            _ => 0.,
+        };
+
+        if let Ok((mut css_transform, _)) = nodes.get_mut(entity) {
+            if css_transform.sticky.y != shift { css_transform.sticky = Vec2::new(0., shift); }
+        }
+    }
+}
+
+// FIXME padding in anything but px doesn't count towards the contents
+fn px(val: Val) -> f32 {
+    match val {
+        Val::Px(px) => px,
+        _ => 0.,
+    }
+}
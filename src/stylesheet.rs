@@ -0,0 +1,48 @@
+use crate::declarations;
+
+// a top-level rule in a stylesheet, e.g. `@font-face { ... }`
+pub(crate) struct Rule {
+    pub(crate) prelude: String, // everything before the {, e.g. "@font-face" or ".button:hover"
+    pub(crate) block: String, // everything between the braces, which may contain nested blocks
+    pub(crate) line: usize, // of the opening brace, starting at 1
+}
+
+// split a stylesheet into its top-level rules, it's up to the caller to make sense of each block
+pub(crate) fn parse(source: &str) -> Vec<Rule> {
+    let source = declarations::strip_comments(source);
+    let mut rules = vec![];
+
+    let mut line = 1;
+    let mut depth = 0;
+    let mut prelude = String::new();
+    let mut block = String::new();
+    let mut block_line = 1;
+
+    for each in source.chars() {
+        match each {
+            '{' if depth == 0 => {
+                depth = 1;
+                block_line = line;
+            }
+            '{' => {
+                depth += 1;
+                block.push(each);
+            }
+            '}' if depth == 1 => {
+                depth = 0;
+                rules.push(Rule { prelude: prelude.trim().into(), block: std::mem::take(&mut block), line: block_line });
+                prelude.clear();
+            }
+            '}' if depth > 1 => {
+                depth -= 1;
+                block.push(each);
+            }
+            _ if depth == 0 => prelude.push(each),
+            _ => block.push(each),
+        }
+
+        if each == '\n' { line += 1; }
+    }
+
+    rules
+}
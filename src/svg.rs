@@ -0,0 +1,151 @@
+use std::fmt;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::utils::BoxedFuture;
+
+use crate::computed::ComputedStyle;
+use crate::effects::{self, Filter};
+
+// the parsed SVG behind an Image loaded from a .svg file, as the labeled asset "source", e.g. heart.svg#source
+#[derive(Asset, TypePath)]
+pub(crate) struct Svg {
+    tree: resvg::usvg::Tree,
+}
+
+#[derive(Default)]
+pub(crate) struct SvgLoader;
+
+#[derive(Debug)]
+pub(crate) enum SvgError {
+    Io(std::io::Error),
+    Parse(resvg::usvg::Error),
+    Empty, // zero width or height
+}
+
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SvgError::Io(error) => write!(f, "could not read SVG: {}", error),
+            SvgError::Parse(error) => write!(f, "could not parse SVG: {}", error),
+            SvgError::Empty => write!(f, "SVG has no size"),
+        }
+    }
+}
+
+impl std::error::Error for SvgError {}
+
+// Loads an SVG as an Image at its own size, like a browser does before any CSS applies. That image is only used
+// until the node has a size, then rasterize() replaces it with one which fits the node exactly.
+impl AssetLoader for SvgLoader {
+    type Asset = Image;
+    type Settings = ();
+    type Error = SvgError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Image, SvgError>> {
+        Box::pin(async move {
+            let mut bytes = vec![];
+            reader.read_to_end(&mut bytes).await.map_err(SvgError::Io)?;
+
+            let tree = resvg::usvg::Tree::from_data(&bytes, &resvg::usvg::Options::default()).map_err(SvgError::Parse)?;
+            let size = tree.size().to_int_size();
+            let image = render(&tree, UVec2::new(size.width(), size.height())).ok_or(SvgError::Empty)?;
+
+            load_context.add_labeled_asset("source".into(), Svg { tree });
+            Ok(image)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["svg"]
+    }
+}
+
+// the SVG stretched to exactly `size` physical pixels, None if either dimension is 0
+fn render(tree: &resvg::usvg::Tree, size: UVec2) -> Option<Image> {
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(size.x, size.y)?;
+    let scale = Vec2::new(size.x as f32 / tree.size().width(), size.y as f32 / tree.size().height());
+    resvg::render(tree, resvg::tiny_skia::Transform::from_scale(scale.x, scale.y), &mut pixmap.as_mut());
+
+    // tiny-skia premultiplies alpha, Bevy images don't
+    let data = pixmap.pixels().iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+
+    Some(Image::new(
+        Extent3d { width: size.x, height: size.y, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ))
+}
+
+// an image node showing an SVG, and the size in physical pixels and filter it was last rasterized with
+#[derive(Component)]
+pub(crate) struct Rasterized {
+    svg: Handle<Svg>,
+    size: UVec2,
+    filter: Vec<Filter>,
+}
+
+// Bevy scales images to fit their node, which blurs an SVG rasterized at its own size, so rasterize it again
+// whenever the node's size or the window's scale factor changes, e.g. at the logo's 28px => 40px breakpoint
+// Bevy can only tint images, so CSS filters are applied to the pixels while rasterizing too
+// FIXME the raster is also the image's intrinsic size, so this only works for images with a height or width set
+// FIXME filter only works on SVGs, other images would need to keep a copy of the original pixels
+pub(crate) fn rasterize(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    window: Query<&Window>,
+    svgs: Res<Assets<Svg>>,
+    mut images: ResMut<Assets<Image>>,
+    mut nodes: Query<(Entity, &Node, &ComputedStyle, &mut UiImage, Option<&mut Rasterized>)>,
+) {
+    let scale_factor = window.single().scale_factor();
+
+    for (entity, node, computed, mut image, rasterized) in nodes.iter_mut() {
+        let size = (node.size() * scale_factor).round().as_uvec2();
+        if size.x == 0 || size.y == 0 { continue; }
+
+        let filter = computed.effects.filter.clone().unwrap_or_default();
+
+        // the first time, find out whether the image came from an SVG
+        let svg = match rasterized.as_ref() {
+            Some(rasterized) if rasterized.size == size && rasterized.filter == filter => continue,
+            Some(rasterized) => rasterized.svg.clone(),
+            None => {
+                let Some(path) = asset_server.get_path(image.texture.id()) else { continue; };
+                let Some(svg) = asset_server.get_handle::<Svg>(path.with_label("source")) else { continue; };
+                svg
+            }
+        };
+
+        // not loaded yet, try again next frame
+        let Some(tree) = svgs.get(&svg).map(|svg| &svg.tree) else { continue; };
+        let Some(mut raster) = render(tree, size) else { continue; };
+        effects::filter(&mut raster.data, &filter);
+
+        // the previous raster is dropped along with its handle
+        image.texture = images.add(raster);
+
+        match rasterized {
+            Some(mut rasterized) => {
+                rasterized.size = size;
+                rasterized.filter = filter;
+            }
+            None => { commands.entity(entity).insert(Rasterized { svg, size, filter }); }
+        }
+    }
+}
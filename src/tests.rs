@@ -0,0 +1,224 @@
+// Lay the page out headlessly at each of the header's breakpoints, and compare every node of the header with the
+// snapshots in src/snapshots/, so that layout changes show up as diffs in review. A missing snapshot fails like
+// a changed one, and UPDATE_SNAPSHOTS=1 writes all of them instead of comparing. Animations are checked the same way,
+// with a clock which only moves when the test says so, and text decorations on a HiDPI window, where glyphs are in
+// physical px and nodes in logical px.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bevy::asset::AssetMetaCheck;
+use bevy::prelude::*;
+use bevy::ecs::system::RunSystemOnce;
+use bevy::render::camera::{camera_system, CameraUpdateSystem, ManualTextureViews};
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::{ExitCondition, PrimaryWindow, WindowResized, WindowResolution};
+
+use crate::animation::Animations;
+use crate::css::{Classes, CSS};
+use crate::html::{self, Tag};
+use crate::text::TextDecoration;
+
+// one between each of the @media(min-width) breakpoints in css::recalculate, 550, 768, 992 and 1200px
+const WIDTHS: [f32; 5] = [500., 700., 900., 1000., 1300.];
+const HEIGHT: f32 = 800.;
+
+// how many updates in a row nothing has to be loading for before layout has settled
+const SETTLED: usize = 10;
+
+// MinimalPlugins plus whatever it takes to load fonts and lay out UI, without a window or a GPU
+fn app(width: f32) -> App {
+    app_with_scale_factor(width, 1.)
+}
+
+// like app(), with a HiDPI window whose physical px are scale_factor logical px
+fn app_with_scale_factor(width: f32, scale_factor: f32) -> App {
+    let mut app = App::new();
+    let resolution = WindowResolution::new(width, HEIGHT).with_scale_factor_override(scale_factor);
+
+    app
+        .insert_resource(AssetMetaCheck::Never)
+        .add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            bevy::a11y::AccessibilityPlugin,
+            bevy::input::InputPlugin,
+            bevy::hierarchy::HierarchyPlugin,
+            bevy::transform::TransformPlugin,
+            WindowPlugin {
+                primary_window: Some(Window { resolution, ..default() }),
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            },
+        ))
+        // bevy_render would add these, along with everything which needs a GPU. The camera's viewport is what
+        // bevy_ui lays out in
+        .init_asset::<Image>()
+        .init_asset::<Shader>()
+        .init_asset::<TextureAtlasLayout>()
+        .init_resource::<ManualTextureViews>()
+        .add_systems(PostUpdate, camera_system::<OrthographicProjection>.in_set(CameraUpdateSystem))
+        .add_plugins((bevy::text::TextPlugin::default(), bevy::ui::UiPlugin::default()))
+        .add_plugins(crate::page);
+
+    app
+}
+
+// resize the window like winit would, which is what spawns the page, then update until the fonts have loaded
+// and the text has been laid out with them
+fn lay_out(app: &mut App, width: f32) {
+    let window = app.world_mut().query_filtered::<Entity, With<PrimaryWindow>>().single(app.world());
+    app.world_mut().send_event(WindowResized { window, width, height: HEIGHT });
+
+    let mut settled = 0;
+    for _ in 0..1000 {
+        app.update();
+
+        let world = app.world();
+        let is_loading = world.resource::<CSS>().fonts.is_loading(world.resource::<AssetServer>());
+        settled = if is_loading { 0 } else { settled + 1 };
+        if settled == SETTLED { return; }
+
+        // fonts are loaded on another thread
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    panic!("fonts were still loading after 1000 updates");
+}
+
+// every node of the header in document order, indented by depth, e.g.
+//   div.header__left-block (16, 12) 200x40
+//     span.header__message (120, 20) 72x24 "Features"
+fn snapshot(world: &mut World) -> String {
+    let header = world.query::<(Entity, &Tag)>().iter(world)
+        .find(|(_, tag)| tag.0 == "header")
+        .map(|(entity, _)| entity)
+        .expect("the page has no <header>");
+
+    let mut nodes = world.query::<(&Tag, Option<&Classes>, &Node, &GlobalTransform, Option<&Text>, Option<&Children>)>();
+    let mut lines = vec![];
+    let mut stack = vec![(header, 0)];
+
+    while let Some((entity, depth)) = stack.pop() {
+        // inline elements have no Node, their text is part of their parent's, see html::inline_formatting_context
+        let Ok((tag, classes, node, transform, text, children)) = nodes.get(world, entity) else { continue; };
+
+        let rect = node.logical_rect(transform);
+        let mut line = format!(
+            "{}{}{} ({:.1}, {:.1}) {:.1}x{:.1}",
+            "  ".repeat(depth), tag.0, classes.map(Classes::selector).unwrap_or_default(),
+            rect.min.x, rect.min.y, rect.width(), rect.height(),
+        );
+        if let Some(text) = text {
+            line += &format!(" {:?}", text.sections.iter().map(|section| section.value.as_str()).collect::<String>());
+        }
+        lines.push(line);
+
+        // reversed, so that they're popped in document order
+        stack.extend(children.into_iter().flatten().rev().map(|child| (*child, depth + 1)));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+// only the lines which differ, - expected and + actual
+fn diff(expected: &str, actual: &str) -> String {
+    let (expected, actual) = (expected.lines().collect::<Vec<&str>>(), actual.lines().collect::<Vec<&str>>());
+
+    (0..expected.len().max(actual.len()))
+        .filter(|line| expected.get(*line) != actual.get(*line))
+        .flat_map(|line| [expected.get(line).map(|each| format!("-{}", each)), actual.get(line).map(|each| format!("+{}", each))])
+        .flatten()
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[test]
+fn header_at_each_breakpoint() {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/snapshots");
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let mut failures = vec![];
+
+    for width in WIDTHS {
+        let mut app = app(width);
+        lay_out(&mut app, width);
+        let actual = snapshot(app.world_mut());
+
+        let path = directory.join(format!("header-{}.txt", width));
+        if update {
+            std::fs::create_dir_all(&directory).unwrap();
+            std::fs::write(&path, actual).unwrap();
+            continue;
+        }
+
+        match std::fs::read_to_string(&path) {
+            Ok(expected) => if expected != actual { failures.push(format!("{}\n{}", path.display(), diff(&expected, &actual))); },
+            Err(error) => failures.push(format!("{}: {}", path.display(), error)),
+        }
+    }
+
+    assert!(failures.is_empty(), "the header's layout changed or has no snapshot, run with UPDATE_SNAPSHOTS=1 if that's intended\n\n{}", failures.join("\n\n"));
+}
+
+// an element which pulses from opacity 1 to 0.5 and back every 2s, see CSS::keyframes_rule
+fn pulse(mut commands: Commands, css: Res<CSS>) {
+    commands.spawn(NodeBundle::default()).with_children(|parent| {
+        let style = "width: 10px; height: 10px; background-color: #ff0000; animation: pulse 2s infinite";
+        html::div(parent, &css, Classes(vec![]), &[("style", style)], |_| {});
+    });
+}
+
+#[test]
+fn pulse_at_half_way() {
+    let mut app = app(WIDTHS[0]);
+    lay_out(&mut app, WIDTHS[0]);
+
+    // every update is 100ms from now on, so the animation starts on the next one and is half way 10 later
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)));
+    app.world_mut().run_system_once(pulse);
+    for _ in 0..11 { app.update(); }
+
+    // animated values are only drawn, ComputedStyle keeps the cascaded opacity of 1
+    let world = app.world_mut();
+    let alpha = world.query::<(&Animations, &BackgroundColor)>().iter(world)
+        .find(|(animations, _)| animations.0.iter().any(|animation| animation.name == "pulse"))
+        .map(|(_, background_color)| background_color.0.alpha())
+        .expect("nothing is animating pulse");
+
+    // 50% { opacity: 0.5; } is a keyframe, so easing doesn't change it
+    assert!((alpha - 0.5).abs() < 1e-3, "expected an opacity of 0.5 half way through pulse, found {}", alpha);
+}
+
+// an underlined line of text, see text::decorate
+fn underlined(mut commands: Commands, css: Res<CSS>) {
+    commands.spawn(NodeBundle::default()).with_children(|parent| {
+        html::text(parent, &css, Classes(vec![]), &[("style", "text-decoration: underline")], "underlined");
+    });
+}
+
+#[test]
+fn underline_at_scale_factor_2() {
+    let mut app = app_with_scale_factor(WIDTHS[0], 2.);
+    lay_out(&mut app, WIDTHS[0]);
+
+    // the text is laid out on the first update, and its underline on the next
+    app.world_mut().run_system_once(underlined);
+    for _ in 0..SETTLED { app.update(); }
+
+    let world = app.world_mut();
+    let text = world.query::<(&Node, &GlobalTransform, &Text)>().iter(world)
+        .find(|(_, _, text)| text.sections.iter().any(|section| section.value == "underlined"))
+        .map(|(node, transform, _)| node.logical_rect(transform))
+        .expect("the text wasn't spawned");
+    let underline = world.query_filtered::<(&Node, &GlobalTransform), With<TextDecoration>>().iter(world)
+        .map(|(node, transform)| node.logical_rect(transform))
+        .next()
+        .expect("the text has no underline");
+
+    // glyphs are in physical px, which would make the underline twice as wide as its text, the glyphs' side
+    // bearings make it a little narrower
+    let is_close = |expected: f32, found: f32| (expected - found).abs() < 0.1 * text.width();
+    assert!(is_close(text.min.x, underline.min.x), "expected the underline to start at {}, found {}", text.min.x, underline.min.x);
+    assert!(is_close(text.width(), underline.width()), "expected the underline to be {} wide, found {}", text.width(), underline.width());
+    assert!(underline.min.y > text.min.y && underline.max.y <= text.max.y + 2., "expected the underline within {:?}, found {:?}", text, underline);
+}
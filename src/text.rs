@@ -0,0 +1,455 @@
+use bevy::hierarchy::HierarchyQueryExt;
+use bevy::prelude::*;
+use bevy::text::{BreakLineOn, TextLayoutInfo};
+use bevy::window::RequestRedraw;
+
+use crate::Link;
+use crate::calc::{Context, Length};
+use crate::computed::ComputedStyle;
+use crate::css::CSS;
+use crate::patch::Patch;
+use crate::scroll::Scroll;
+use crate::transform::{self, CssTransform};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum WhiteSpace {
+    #[default]
+    Normal,
+    NoWrap,
+    Pre,
+    PreWrap,
+}
+
+impl WhiteSpace {
+    // runs of spaces, tabs and newlines become a single space
+    fn collapses(&self) -> bool {
+        matches!(self, WhiteSpace::Normal | WhiteSpace::NoWrap)
+    }
+
+    fn break_line_on(&self) -> BreakLineOn {
+        match self {
+            WhiteSpace::Normal | WhiteSpace::PreWrap => BreakLineOn::WordBoundary,
+            WhiteSpace::NoWrap | WhiteSpace::Pre => BreakLineOn::NoWrap,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum LineHeight {
+    Number(f32), // times the font-size, e.g. line-height: 1.5
+    Length(Length),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum TextOverflow {
+    #[default]
+    Clip,
+    Ellipsis,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum TextTransform {
+    #[default]
+    None,
+    Uppercase,
+    Lowercase,
+    Capitalize,
+}
+
+// text-decoration-line, FIXME no overline
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct DecorationLine {
+    pub(crate) underline: bool,
+    pub(crate) line_through: bool,
+}
+
+// text properties which Bevy's Text has no equivalent for, None means "not declared"
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct TextProperties {
+    pub(crate) white_space: Option<WhiteSpace>,
+    pub(crate) line_height: Option<LineHeight>,
+    pub(crate) text_overflow: Option<TextOverflow>, // not inherited
+    pub(crate) text_transform: Option<TextTransform>,
+    pub(crate) text_align: Option<JustifyText>,
+    // FIXME decorations aren't inherited in CSS, they're drawn across the descendants instead, which looks the
+    // same except that a descendant can't change their color
+    pub(crate) decoration_line: Option<DecorationLine>,
+    pub(crate) decoration_color: Option<Color>, // None is currentColor
+    pub(crate) decoration_thickness: Option<Length>,
+}
+
+impl Patch for TextProperties {
+    fn apply(&self, patch: &Self) -> Self {
+        TextProperties {
+            white_space: patch.white_space.or(self.white_space),
+            line_height: patch.line_height.clone().or(self.line_height.clone()),
+            text_overflow: patch.text_overflow.or(self.text_overflow),
+            text_transform: patch.text_transform.or(self.text_transform),
+            text_align: patch.text_align.or(self.text_align),
+            decoration_line: patch.decoration_line.or(self.decoration_line),
+            decoration_color: patch.decoration_color.or(self.decoration_color),
+            decoration_thickness: patch.decoration_thickness.clone().or(self.decoration_thickness.clone()),
+        }
+    }
+}
+
+// the parts of the text layout which are decided by the element as a whole, called from css::restyle
+pub(crate) fn restyle(text: &mut Text, computed: &ComputedStyle) {
+    text.linebreak_behavior = computed.text_properties.white_space.unwrap_or_default().break_line_on();
+    text.justify = computed.text_properties.text_align.unwrap_or_default();
+}
+
+// The content of every section of an element's Text as it was written, and the inline element (e.g. <strong>)
+// which styles that section, or None for text directly inside of the element, see html::p
+#[derive(Component)]
+pub(crate) struct InlineSections {
+    pub(crate) spans: Vec<Option<Entity>>,
+    pub(crate) content: Vec<String>,
+}
+
+// Set the style and value of every section from the ComputedStyle of the element or inline element it belongs
+// to, after css::restyle. The value is always derived from the content as written, so white-space and
+// text-transform can be changed back by e.g. :hover
+pub(crate) fn sections(
+    mut elements: Query<(Ref<ComputedStyle>, &InlineSections, &mut Text)>,
+    spans: Query<Ref<ComputedStyle>>,
+) {
+    for (computed, sections, mut text) in elements.iter_mut() {
+        let is_changed = computed.is_changed() || sections.spans.iter().flatten()
+            .any(|span| spans.get(*span).is_ok_and(|computed| computed.is_changed()));
+
+        if !is_changed { continue; }
+
+        // capitalize continues across sections, e.g. <strong>b</strong>old is one word
+        let mut is_word_start = true;
+
+        text.sections = sections.content.iter().zip(sections.spans.iter())
+            .map(|(content, span)| {
+                let span = span.and_then(|span| spans.get(span).ok());
+                let computed = span.as_deref().unwrap_or(&computed);
+                let properties = &computed.text_properties;
+
+                let white_space = properties.white_space.unwrap_or_default();
+                let value = if white_space.collapses() { collapse(content) } else { content.clone() };
+                let value = transform(&value, properties.text_transform.unwrap_or_default(), &mut is_word_start);
+
+                TextSection::new(value, section_style(computed))
+            })
+            .collect();
+    }
+}
+
+// the style of the sections of an element or inline element, also used by transition::animate
+pub(crate) fn section_style(computed: &ComputedStyle) -> TextStyle {
+    TextStyle { color: computed.with_opacity(computed.text.color), ..computed.text.clone() }
+}
+
+fn collapse(value: &str) -> String {
+    let mut collapsed = String::with_capacity(value.len());
+    for each in value.chars() {
+        if !each.is_whitespace() {
+            collapsed.push(each);
+        } else if !collapsed.ends_with(' ') {
+            collapsed.push(' ');
+        }
+    }
+    collapsed
+}
+
+fn transform(value: &str, text_transform: TextTransform, is_word_start: &mut bool) -> String {
+    let mut transformed = String::with_capacity(value.len());
+    for each in value.chars() {
+        match text_transform {
+            TextTransform::None => transformed.push(each),
+            TextTransform::Uppercase => transformed.extend(each.to_uppercase()),
+            TextTransform::Lowercase => transformed.extend(each.to_lowercase()),
+            TextTransform::Capitalize if *is_word_start => transformed.extend(each.to_uppercase()),
+            TextTransform::Capitalize => transformed.push(each),
+        }
+        *is_word_start = each.is_whitespace();
+    }
+    transformed
+}
+
+// Bevy only tracks the Interaction of whole nodes, so find the inline link under the cursor from the glyphs,
+// which then gets :hover and the pointer cursor like any other Link
+pub(crate) fn inline_interaction(
+    window: Query<&Window>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    elements: Query<(&Node, &GlobalTransform, &TextLayoutInfo, &InlineSections)>,
+    parents: Query<&Parent>,
+    mut links: Query<(Entity, &mut Interaction), (With<Link>, Without<Node>)>,
+) {
+    let window = window.single();
+    let scale_factor = window.scale_factor();
+    let mut hovered = None;
+
+    if let Some(cursor) = window.cursor_position() {
+        for (node, transform, layout, sections) in elements.iter() {
+            let rect = node.logical_rect(transform);
+            if !rect.contains(cursor) { continue; }
+
+            // glyph positions are the centers of the glyphs, relative to the top left of the node, in physical px
+            let section = layout.glyphs.iter()
+                .find(|glyph| Rect::from_center_size(rect.min + glyph.position / scale_factor, glyph.size / scale_factor).contains(cursor))
+                .and_then(|glyph| sections.spans.get(glyph.section_index).copied().flatten());
+
+            // the link may be an ancestor of the section's element, e.g. <a><strong>...</strong></a>
+            if let Some(span) = section {
+                hovered = std::iter::once(span).chain(parents.iter_ancestors(span)).find(|entity| links.contains(*entity));
+            }
+        }
+    }
+
+    for (entity, mut interaction) in links.iter_mut() {
+        let new = match hovered == Some(entity) {
+            true if mouse.pressed(MouseButton::Left) => Interaction::Pressed,
+            true => Interaction::Hovered,
+            false => Interaction::None,
+        };
+
+        // only write when it changes, css::hover reacts to Changed<Interaction>
+        if *interaction != new { *interaction = new; }
+    }
+}
+
+// (line-height, line-height: normal) in px
+// FIXME line-height: normal depends on the font, 1.2 is what most browsers use for most fonts
+fn line_height(computed: &ComputedStyle, context: &Context) -> (f32, f32) {
+    let font_size = computed.text.font_size;
+    let normal = 1.2 * font_size;
+
+    let line_height = match &computed.text_properties.line_height {
+        Some(LineHeight::Number(number)) => number * font_size,
+        Some(LineHeight::Length(length)) => length.evaluate(context),
+        None => normal,
+    };
+
+    (line_height, normal)
+}
+
+// the line of every glyph, glyphs are in text order and a new line starts wherever x jumps back to the left
+fn lines(layout: &TextLayoutInfo) -> Vec<usize> {
+    let mut line = 0;
+    let mut previous_x = f32::NEG_INFINITY;
+
+    layout.glyphs.iter()
+        .map(|glyph| {
+            if glyph.position.x < previous_x { line += 1; }
+            previous_x = glyph.position.x;
+            line
+        })
+        .collect()
+}
+
+// Bevy has no letter-spacing, word-spacing or line-height, so move the glyphs around after Bevy has laid out
+// the text, and cut off text which overflows with an ellipsis
+// FIXME the node isn't resized to fit, so wider or taller text overflows its node (and smaller text leaves a gap)
+pub(crate) fn adjust(
+    css: Res<CSS>,
+    window: Query<&Window>,
+    mut texts: Query<(&ComputedStyle, &Node, &mut Text, &mut TextLayoutInfo), Changed<TextLayoutInfo>>,
+) {
+    let window = window.single();
+    let viewport = Vec2::new(window.width(), window.height());
+    // glyphs are laid out in physical px, lengths are in logical px
+    let scale_factor = window.scale_factor();
+
+    for (computed, node, mut text, mut layout) in texts.iter_mut() {
+        let font_size = computed.text.font_size;
+        let context = Context { percent_of: font_size, font_size, rem: css.rem, viewport };
+
+        let letter_spacing = scale_factor * computed.letter_spacing.as_ref().map_or(0., |length| length.evaluate(&context));
+        let word_spacing = scale_factor * computed.word_spacing.as_ref().map_or(0., |length| length.evaluate(&context));
+
+        let (line_height, normal) = line_height(computed, &context);
+        let leading = scale_factor * (line_height - normal);
+
+        // glyphs know their byte index within their own section, so index into all sections as one string
+        let value = text.sections.iter().map(|section| section.value.as_str()).collect::<String>();
+        let offsets = text.sections.iter()
+            .scan(0, |offset, section| {
+                let start = *offset;
+                *offset += section.value.len();
+                Some(start)
+            })
+            .collect::<Vec<usize>>();
+
+        // Bevy only lays the text out again when it changes, so don't trigger Changed<TextLayoutInfo> ourselves,
+        // or the glyphs would be moved again next frame
+        let layout = layout.bypass_change_detection();
+
+        if letter_spacing != 0. || word_spacing != 0. || leading != 0. {
+            let lines = lines(layout);
+            let mut line_start = 0;
+
+            for (index, glyph) in layout.glyphs.iter_mut().enumerate() {
+                let line = lines[index];
+                let byte = offsets[glyph.section_index] + glyph.byte_index;
+                if index == 0 || lines[index - 1] != line { line_start = byte; }
+
+                let before = value.get(line_start..byte).unwrap_or_default();
+                glyph.position.x += letter_spacing * before.chars().count() as f32 + word_spacing * before.matches(' ').count() as f32;
+                // half of the extra leading goes above each line, and half below
+                glyph.position.y += leading / 2. + leading * line as f32;
+            }
+        }
+
+        let is_clipped = computed.style.overflow.x == OverflowAxis::Clip;
+        if is_clipped && computed.text_properties.text_overflow == Some(TextOverflow::Ellipsis) {
+            // FIXME the width of the ellipsis depends on the font, 1em is always enough
+            let width = scale_factor * node.size().x;
+            let available = width - scale_factor * font_size;
+            let overflowing = layout.glyphs.iter()
+                .find(|glyph| glyph.position.x + glyph.size.x / 2. > width)
+                .and_then(|_| layout.glyphs.iter().rev().find(|glyph| glyph.position.x + glyph.size.x / 2. <= available))
+                .map(|last| (last.section_index, last.byte_index));
+
+            // cut the text after the last glyph which leaves room for the ellipsis, then Bevy lays it out again
+            // text::sections puts the whole text back whenever the element is restyled
+            // FIXME the cut text isn't restored when the node grows, but the page is rebuilt on resize anyway
+            if let Some((section_index, byte_index)) = overflowing {
+                let section = &mut text.sections[section_index];
+                let end = section.value[byte_index..].chars().next().map_or(byte_index, |each| byte_index + each.len_utf8());
+                section.value = format!("{}…", section.value[..end].trim_end());
+                text.sections.truncate(section_index + 1);
+            }
+        }
+    }
+}
+
+// an underline or line-through, which is a node next to its text, see decorate()
+#[derive(Component)]
+pub(crate) struct TextDecoration {
+    text: Entity,
+}
+
+// the decorations of some text, in the order decorate() drew them
+#[derive(Component)]
+pub(crate) struct TextDecorations(Vec<Entity>);
+
+// Bevy can't underline text, so draw each decoration as a thin node along a run of glyphs, after text::adjust.
+// They're siblings of their text, since bevy_ui would lay out a text node with children as a flex container
+// rather than measure its text, so they're clipped and scrolled along with it. They're only updated when
+// their text has moved or changed, and are reused rather than spawned again.
+// FIXME in front of every earlier sibling of the text, even ones with a higher z-index
+pub(crate) fn decorate(
+    mut commands: Commands,
+    css: Res<CSS>,
+    window: Query<&Window>,
+    mut texts: Query<
+        (Entity, &ComputedStyle, &Node, &Transform, &TextLayoutInfo, Option<&InlineSections>, &Parent, Option<&mut TextDecorations>),
+        (With<Text>, Or<(Changed<TextLayoutInfo>, Changed<Transform>, Changed<ComputedStyle>)>),
+    >,
+    parents: Query<(&Node, &Style, Option<&Scroll>), Without<TextDecoration>>,
+    spans: Query<&ComputedStyle, Without<Text>>,
+    mut nodes: Query<(&mut Style, &mut BackgroundColor), With<TextDecoration>>,
+    decorations: Query<(Entity, &TextDecoration)>,
+    alive: Query<(), With<Text>>,
+    mut removed: RemovedComponents<Text>,
+    mut redraw: EventWriter<RequestRedraw>,
+) {
+    let window = window.single();
+    let viewport = Vec2::new(window.width(), window.height());
+    let scale_factor = window.scale_factor();
+    let mut changed = false;
+
+    for (entity, computed, node, transform, layout, sections, parent, existing) in texts.iter_mut() {
+        let Ok((parent_node, parent_style, scroll)) = parents.get(parent.get()) else { continue; };
+
+        // where layout put the text within its parent's padding box, which is what absolute nodes are placed in
+        let scroll = scroll.map_or(Vec2::ZERO, |scroll| scroll.offset);
+        let border = Vec2::new(
+            transform::resolve(parent_style.border.left, parent_node.size().x, viewport),
+            transform::resolve(parent_style.border.top, parent_node.size().x, viewport),
+        );
+        let origin = transform.translation.truncate() + scroll + (parent_node.size() - node.size()) / 2. - border;
+
+        // consecutive glyphs of the same section on the same line, as (line, section index, extent in logical px)
+        let mut runs: Vec<(usize, usize, Rect)> = vec![];
+        for (glyph, line) in layout.glyphs.iter().zip(lines(layout)) {
+            let extent = Rect::from_center_size(glyph.position / scale_factor, glyph.size / scale_factor);
+            match runs.last_mut() {
+                Some((run_line, section, rect)) if *run_line == line && *section == glyph.section_index => *rect = rect.union(extent),
+                _ => runs.push((line, glyph.section_index, extent)),
+            }
+        }
+
+        let mut wanted = vec![];
+        for (line, section, extent) in runs {
+            let span = sections.and_then(|sections| sections.spans.get(section).copied().flatten());
+            let computed = span.and_then(|span| spans.get(span).ok()).unwrap_or(computed);
+            let properties = &computed.text_properties;
+
+            let Some(decoration_line) = properties.decoration_line else { continue; };
+
+            let font_size = computed.text.font_size;
+            let context = Context { percent_of: font_size, font_size, rem: css.rem, viewport };
+            let (line_height, normal) = line_height(computed, &context);
+            let line_top = (line_height - normal) / 2. + line_height * line as f32;
+
+            // text-decoration-thickness: auto
+            let thickness = properties.decoration_thickness.as_ref()
+                .map(|length| length.evaluate(&context))
+                .unwrap_or((font_size / 16.).max(1.));
+            let color = computed.with_opacity(properties.decoration_color.unwrap_or(computed.text.color));
+
+            // FIXME these depend on the baseline and x-height of the font, which Bevy doesn't expose
+            let mut offsets = vec![];
+            if decoration_line.underline { offsets.push(1.0 * font_size); }
+            if decoration_line.line_through { offsets.push(0.6 * font_size); }
+
+            for offset in offsets {
+                let position = origin + Vec2::new(extent.min.x, line_top + offset);
+                let style = Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(position.x),
+                    top: Val::Px(position.y),
+                    width: Val::Px(extent.width()),
+                    height: Val::Px(thickness),
+                    ..default()
+                };
+                wanted.push((style, BackgroundColor(color)));
+            }
+        }
+
+        let mut entities = existing.as_ref().map(|existing| existing.0.clone()).unwrap_or_default();
+
+        // move and recolor the decorations which are already there, then add or remove the difference
+        for (entity, (style, color)) in entities.iter().zip(wanted.iter()) {
+            let Ok((mut node_style, mut node_color)) = nodes.get_mut(*entity) else { continue; };
+            changed |= node_style.set_if_neq(style.clone());
+            changed |= node_color.set_if_neq(*color);
+        }
+        for entity in entities.iter().skip(wanted.len()) {
+            commands.entity(*entity).despawn_recursive();
+            changed = true;
+        }
+        entities.truncate(wanted.len());
+        for (style, background_color) in wanted.into_iter().skip(entities.len()) {
+            let decoration = commands.spawn((
+                NodeBundle { style, background_color, ..default() },
+                CssTransform::default(), // scrolled along with the text
+                TextDecoration { text: entity },
+            )).set_parent(parent.get()).id();
+            entities.push(decoration);
+            changed = true;
+        }
+
+        match existing {
+            Some(mut existing) => if existing.0 != entities { existing.0 = entities; },
+            None => { commands.entity(entity).insert(TextDecorations(entities)); }
+        }
+    }
+
+    // decorations of text which is gone, unless they went with it
+    if removed.read().count() > 0 {
+        for (entity, decoration) in decorations.iter() {
+            if alive.contains(decoration.text) { continue; }
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    // new and moved nodes are only laid out next frame, see desktop_app() in main
+    if changed { redraw.send(RequestRedraw); }
+}
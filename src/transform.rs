@@ -0,0 +1,236 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::calc::Length;
+use crate::patch::Patch;
+use crate::computed::ComputedStyle;
+use crate::scroll::{Position, Scroll};
+use crate::transition;
+
+// a single <transform-function>, e.g. rotate(45deg)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TransformFunction {
+    Translate(Val, Val), // % of the element's own size
+    Scale(f32, f32),
+    Rotate(f32), // radians, clockwise
+}
+
+impl TransformFunction {
+    // the same function, doing nothing, which is what `none` is interpolated as
+    fn identity(&self) -> Self {
+        match self {
+            TransformFunction::Translate(..) => TransformFunction::Translate(Val::Px(0.), Val::Px(0.)),
+            TransformFunction::Scale(..) => TransformFunction::Scale(1., 1.),
+            TransformFunction::Rotate(..) => TransformFunction::Rotate(0.),
+        }
+    }
+
+    // None if they're different functions
+    fn interpolate(&self, to: &Self, t: f32) -> Option<Self> {
+        let lerp = |from: f32, to: f32| from + (to - from) * t;
+
+        match (*self, *to) {
+            (TransformFunction::Translate(x1, y1), TransformFunction::Translate(x2, y2)) => Some(TransformFunction::Translate(
+                transition::interpolate_val(x1, x2, t),
+                transition::interpolate_val(y1, y2, t),
+            )),
+            (TransformFunction::Scale(x1, y1), TransformFunction::Scale(x2, y2)) => Some(TransformFunction::Scale(lerp(x1, x2), lerp(y1, y2))),
+            (TransformFunction::Rotate(from), TransformFunction::Rotate(to)) => Some(TransformFunction::Rotate(lerp(from, to))),
+            _ => None,
+        }
+    }
+}
+
+// transform and transform-origin, None means "not declared"
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct TransformProperties {
+    pub(crate) transform: Option<Vec<TransformFunction>>, // transform: none is Some(vec![])
+    pub(crate) origin: Option<(Val, Val)>, // from the top left corner
+}
+
+impl Patch for TransformProperties {
+    fn apply(&self, patch: &Self) -> Self {
+        TransformProperties {
+            transform: patch.transform.clone().or(self.transform.clone()),
+            origin: patch.origin.or(self.origin),
+        }
+    }
+}
+
+// the transform which css::draw last drew, applied to the node's Transform by apply() once it's been laid out
+#[derive(Component, Default)]
+pub(crate) struct CssTransform {
+    pub(crate) properties: TransformProperties,
+    // how far scroll::stick moved it to keep it in view
+    pub(crate) sticky: Vec2,
+    // where bevy_ui last put the node, and where apply() moved it to from there
+    layout: Vec3,
+    applied: Option<Vec3>,
+}
+
+impl CssTransform {
+    // where bevy_ui laid the node out, which is where it still is unless layout has moved it since apply() did
+    pub(crate) fn layout(&mut self, transform: &Transform) -> Vec3 {
+        if self.applied != Some(transform.translation) {
+            self.layout = transform.translation;
+        }
+        self.layout
+    }
+}
+
+// e.g. "translateY(-2px) scale(1.05)"
+pub(crate) fn parse(value: &str, rem: f32) -> Result<Vec<TransformFunction>, String> {
+    if value == "none" { return Ok(vec![]); }
+
+    let length = |argument: &str| Length::parse(argument)?.to_val(rem)
+        .ok_or_else(|| format!("translate() only supports px, rem, % and viewport units, not `{}`", argument));
+    let number = |argument: &str| argument.parse::<f32>().map_err(|_| format!("invalid number `{}`", argument));
+
+    let mut functions = vec![];
+    let mut rest = value.trim();
+
+    while !rest.is_empty() {
+        let open = rest.find('(').ok_or_else(|| format!("expected a transform function, found `{}`", rest))?;
+        let close = open + rest[open..].find(')').ok_or("missing `)`")?;
+        let name = rest[..open].trim();
+        let arguments = rest[open + 1..close].split(',').map(str::trim).collect::<Vec<&str>>();
+
+        functions.push(match (name, arguments.as_slice()) {
+            ("translate", [x]) => TransformFunction::Translate(length(x)?, Val::Px(0.)),
+            ("translate", [x, y]) => TransformFunction::Translate(length(x)?, length(y)?),
+            ("translateX", [x]) => TransformFunction::Translate(length(x)?, Val::Px(0.)),
+            ("translateY", [y]) => TransformFunction::Translate(Val::Px(0.), length(y)?),
+            ("scale", [both]) => TransformFunction::Scale(number(both)?, number(both)?),
+            ("scale", [x, y]) => TransformFunction::Scale(number(x)?, number(y)?),
+            ("scaleX", [x]) => TransformFunction::Scale(number(x)?, 1.),
+            ("scaleY", [y]) => TransformFunction::Scale(1., number(y)?),
+            ("rotate", [angle]) => TransformFunction::Rotate(self::angle(angle)?),
+            // FIXME no skew(), matrix() or 3D transforms
+            ("translate" | "translateX" | "translateY" | "scale" | "scaleX" | "scaleY" | "rotate", _) => {
+                Err(format!("wrong number of arguments for {}()", name))?
+            }
+            _ => Err(format!("unsupported transform function `{}()`", name))?
+        });
+
+        rest = rest[close + 1..].trim_start();
+    }
+
+    Ok(functions)
+}
+
+// an <angle> in radians, e.g. 45deg or 0.25turn
+fn angle(value: &str) -> Result<f32, String> {
+    let units = [("deg", PI / 180.), ("grad", PI / 200.), ("rad", 1.), ("turn", TAU)];
+    let (number, unit) = units.iter()
+        .find_map(|(unit, radians)| value.strip_suffix(unit).map(|number| (number, *radians)))
+        .unwrap_or((value, if value == "0" { 1. } else { f32::NAN }));
+
+    match number.parse::<f32>() {
+        Ok(number) if !unit.is_nan() => Ok(number * unit),
+        _ => Err(format!("invalid angle `{}`", value)),
+    }
+}
+
+// e.g. "left top", "50% 100%" or "center"
+pub(crate) fn parse_origin(value: &str, rem: f32) -> Result<(Val, Val), String> {
+    let keyword = |token: &str| match token {
+        "left" | "top" => Some(Val::Percent(0.)),
+        "center" => Some(Val::Percent(50.)),
+        "right" | "bottom" => Some(Val::Percent(100.)),
+        _ => None,
+    };
+    let position = |token: &str| keyword(token).map(Ok).unwrap_or_else(|| {
+        Length::parse(token)?.to_val(rem).ok_or_else(|| format!("transform-origin only supports px, rem, % and viewport units, not `{}`", token))
+    });
+
+    // a vertical keyword on its own or first, e.g. `top` or `bottom left`, is the y
+    let is_vertical = |token: &str| matches!(token, "top" | "bottom");
+    let is_horizontal = |token: &str| matches!(token, "left" | "right");
+
+    match *value.split_whitespace().collect::<Vec<&str>>().as_slice() {
+        [y] if is_vertical(y) => Ok((Val::Percent(50.), position(y)?)),
+        [x] => Ok((position(x)?, Val::Percent(50.))),
+        [y, x] if is_vertical(y) || is_horizontal(x) => Ok((position(x)?, position(y)?)),
+        [x, y] => Ok((position(x)?, position(y)?)),
+        // FIXME no z offset
+        _ => Err(format!("invalid transform-origin `{}`", value)),
+    }
+}
+
+// interpolate two transform lists function by function, where none is the same functions doing nothing
+pub(crate) fn interpolate(from: &[TransformFunction], to: &[TransformFunction], t: f32) -> Vec<TransformFunction> {
+    let identity = |functions: &[TransformFunction]| functions.iter().map(TransformFunction::identity).collect::<Vec<_>>();
+    let (from, to) = match (from.is_empty(), to.is_empty()) {
+        (true, false) => (identity(to), to.to_vec()),
+        (false, true) => (from.to_vec(), identity(from)),
+        _ => (from.to_vec(), to.to_vec()),
+    };
+
+    let functions = (from.len() == to.len())
+        .then(|| from.iter().zip(to.iter()).map(|(from, to)| from.interpolate(to, t)).collect::<Option<Vec<_>>>())
+        .flatten();
+
+    // FIXME lists with different functions would have to be interpolated as matrices, so they flip halfway instead
+    functions.unwrap_or_else(|| if t < 0.5 { from } else { to })
+}
+
+// a Val along one axis of an element of `size`, in px
+pub(crate) fn resolve(val: Val, size: f32, viewport: Vec2) -> f32 {
+    match val {
+        Val::Auto => 0.,
+        Val::Px(px) => px,
+        Val::Percent(percent) => size * percent / 100.,
+        Val::Vw(vw) => viewport.x * vw / 100.,
+        Val::Vh(vh) => viewport.y * vh / 100.,
+        Val::VMin(vmin) => viewport.min_element() * vmin / 100.,
+        Val::VMax(vmax) => viewport.max_element() * vmax / 100.,
+    }
+}
+
+// Move, scale and rotate every node around its transform-origin, on top of where bevy_ui laid it out and
+// wherever its parent is scrolled to. Layout only writes Transform::translation, so flex layout never sees
+// either, just like in a browser.
+// FIXME Interaction, text decorations and the debug overlay still use the untransformed rectangle
+pub(crate) fn apply(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut nodes: Query<(&Node, &mut CssTransform, &mut Transform, Option<&Parent>, Option<&ComputedStyle>)>,
+    parents: Query<&Parent>,
+    scrolls: Query<&Scroll>,
+) {
+    let viewport = windows.get_single().map_or(Vec2::ZERO, |window| window.size());
+    let offset = |entity: Entity| scrolls.get(entity).map_or(Vec2::ZERO, |scroll| scroll.offset);
+
+    for (node, mut css_transform, mut transform, parent, computed) in nodes.iter_mut() {
+        let position = computed.map_or(Position::Relative, |computed| computed.scroll.position());
+        let scroll = match (parent, position) {
+            // fixed elements don't move when anything scrolls, so undo what their parent's ancestors scrolled by
+            // FIXME they're still positioned within their parent like absolute elements, not the viewport
+            (Some(parent), Position::Fixed) => -parents.iter_ancestors(parent.get()).map(offset).sum::<Vec2>(),
+            (Some(parent), _) => offset(parent.get()),
+            (None, _) => Vec2::ZERO,
+        };
+        let layout = css_transform.layout(&transform) - (scroll - css_transform.sticky).extend(0.);
+
+        let size = node.size();
+        let resolve = |val: Val, size: f32| resolve(val, size, viewport);
+        let properties = &css_transform.properties;
+
+        let matrix = properties.transform.iter().flatten().fold(Mat4::IDENTITY, |matrix, function| matrix * match *function {
+            TransformFunction::Translate(x, y) => Mat4::from_translation(Vec3::new(resolve(x, size.x), resolve(y, size.y), 0.)),
+            TransformFunction::Scale(x, y) => Mat4::from_scale(Vec3::new(x, y, 1.)),
+            TransformFunction::Rotate(angle) => Mat4::from_rotation_z(angle),
+        });
+
+        // the origin is from the top left corner, but the node's Transform is around its center
+        let (x, y) = properties.origin.unwrap_or((Val::Percent(50.), Val::Percent(50.)));
+        let origin = Vec3::new(resolve(x, size.x) - size.x / 2., resolve(y, size.y) - size.y / 2., 0.);
+
+        let matrix = Mat4::from_translation(layout + origin) * matrix * Mat4::from_translation(-origin);
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+
+        transform.set_if_neq(Transform { translation, rotation, scale });
+        css_transform.applied = Some(transform.translation);
+    }
+}
@@ -0,0 +1,298 @@
+use bevy::color::Mix;
+use bevy::prelude::*;
+
+use crate::computed::ComputedStyle;
+use crate::patch::Patch;
+use crate::transform::{self, TransformFunction};
+
+// an <easing-function>, every keyword is a cubic-bezier() with the first point at (0, 0) and the last at (1, 1)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct CubicBezier {
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+}
+
+impl CubicBezier {
+    pub(crate) const EASE: Self = Self { x1: 0.25, y1: 0.1, x2: 0.25, y2: 1. };
+    pub(crate) const LINEAR: Self = Self { x1: 0., y1: 0., x2: 1., y2: 1. };
+    pub(crate) const EASE_IN: Self = Self { x1: 0.42, y1: 0., x2: 1., y2: 1. };
+    pub(crate) const EASE_OUT: Self = Self { x1: 0., y1: 0., x2: 0.58, y2: 1. };
+    pub(crate) const EASE_IN_OUT: Self = Self { x1: 0.42, y1: 0., x2: 0.58, y2: 1. };
+
+    pub(crate) fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "ease" => Ok(Self::EASE),
+            "linear" => Ok(Self::LINEAR),
+            "ease-in" => Ok(Self::EASE_IN),
+            "ease-out" => Ok(Self::EASE_OUT),
+            "ease-in-out" => Ok(Self::EASE_IN_OUT),
+            _ => {
+                // FIXME no steps() or linear() with stops
+                let arguments = value.strip_prefix("cubic-bezier(").and_then(|rest| rest.strip_suffix(')'))
+                    .ok_or_else(|| format!("unsupported easing function `{}`", value))?
+                    .split(',')
+                    .map(|each| each.trim().parse::<f32>())
+                    .collect::<Result<Vec<f32>, _>>()
+                    .map_err(|_| format!("invalid number in `{}`", value))?;
+
+                match *arguments.as_slice() {
+                    // x has to stay within 0 to 1, so that there's a single y for every point in time
+                    [x1, y1, x2, y2] if (0. ..=1.).contains(&x1) && (0. ..=1.).contains(&x2) => Ok(Self { x1, y1, x2, y2 }),
+                    [_, _, _, _] => Err(format!("x values must be between 0 and 1 in `{}`", value)),
+                    _ => Err(format!("expected 4 numbers in `{}`", value)),
+                }
+            }
+        }
+    }
+
+    // progress (0 to 1) => eased progress, which can overshoot
+    pub(crate) fn ease(&self, t: f32) -> f32 {
+        if t <= 0. { return 0.; }
+        if t >= 1. { return 1.; }
+
+        // one coordinate of the curve at parameter s, with P0 = 0 and P3 = 1
+        let sample = |p1: f32, p2: f32, s: f32| 3. * (1. - s) * (1. - s) * s * p1 + 3. * (1. - s) * s * s * p2 + s * s * s;
+
+        // x always increases with s, so find the s for which x is t by bisection
+        let (mut low, mut high) = (0., 1.);
+        let mut s = t;
+        for _ in 0..24 {
+            let x = sample(self.x1, self.x2, s);
+            if (x - t).abs() < 1e-6 { break; }
+            if x < t { low = s; } else { high = s; }
+            s = (low + high) / 2.;
+        }
+
+        sample(self.y1, self.y2, s)
+    }
+}
+
+// transition-property, -duration, -timing-function and -delay, None means "not declared"
+// the lists are matched up by index, and repeat if they're shorter than transition-property
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct TransitionProperties {
+    pub(crate) property: Option<Vec<String>>,
+    pub(crate) duration: Option<Vec<f32>>, // in seconds
+    pub(crate) timing_function: Option<Vec<CubicBezier>>,
+    pub(crate) delay: Option<Vec<f32>>, // in seconds
+}
+
+impl Patch for TransitionProperties {
+    fn apply(&self, patch: &Self) -> Self {
+        TransitionProperties {
+            property: patch.property.clone().or(self.property.clone()),
+            duration: patch.duration.clone().or(self.duration.clone()),
+            timing_function: patch.timing_function.clone().or(self.timing_function.clone()),
+            delay: patch.delay.clone().or(self.delay.clone()),
+        }
+    }
+}
+
+impl TransitionProperties {
+    // (duration, timing function, delay) of a property, if it has a transition which takes any time at all
+    fn of(&self, property: &str) -> Option<(f32, CubicBezier, f32)> {
+        // the initial value is `all 0s`
+        let all = vec!["all".to_string()];
+        let properties = self.property.as_ref().unwrap_or(&all);
+        let index = properties.iter().position(|each| each == property || each == "all")?;
+
+        let duration = self.duration.as_ref().and_then(|each| each.get(index % each.len())).copied().unwrap_or(0.);
+        let easing = self.timing_function.as_ref().and_then(|each| each.get(index % each.len())).copied().unwrap_or(CubicBezier::EASE);
+        let delay = self.delay.as_ref().and_then(|each| each.get(index % each.len())).copied().unwrap_or(0.);
+
+        (duration > 0.).then_some((duration, easing, delay))
+    }
+}
+
+// a <time> in seconds, e.g. 100ms or .3s
+pub(crate) fn time(value: &str) -> Result<f32, String> {
+    let seconds = match (value.strip_suffix("ms"), value.strip_suffix('s')) {
+        (Some(ms), _) => ms.parse::<f32>().map(|ms| ms / 1000.),
+        (None, Some(s)) => s.parse::<f32>(),
+        (None, None) if value == "0" => Ok(0.),
+        (None, None) => Err(format!("expected a time in s or ms, found `{}`", value))?,
+    };
+    seconds.map_err(|_| format!("invalid time `{}`", value))
+}
+
+// the value of an animatable property
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    Color(Color),
+    Number(f32),
+    Val(Val),
+    Transform(Vec<TransformFunction>),
+}
+
+impl Value {
+    pub(crate) fn interpolate(&self, to: &Value, t: f32) -> Value {
+        match (self, to) {
+            (Value::Color(from), Value::Color(to)) => Value::Color(Srgba::from(*from).mix(&Srgba::from(*to), t).into()),
+            (Value::Number(from), Value::Number(to)) => Value::Number(from + (to - from) * t),
+            (Value::Val(from), Value::Val(to)) => Value::Val(interpolate_val(*from, *to, t)),
+            (Value::Transform(from), Value::Transform(to)) => Value::Transform(transform::interpolate(from, to, t)),
+            _ => if t < 0.5 { self.clone() } else { to.clone() },
+        }
+    }
+}
+
+pub(crate) fn interpolate_val(from: Val, to: Val, t: f32) -> Val {
+    let lerp = |from: f32, to: f32| from + (to - from) * t;
+
+    match (from, to) {
+        (Val::Px(from), Val::Px(to)) => Val::Px(lerp(from, to)),
+        (Val::Percent(from), Val::Percent(to)) => Val::Percent(lerp(from, to)),
+        (Val::Vw(from), Val::Vw(to)) => Val::Vw(lerp(from, to)),
+        (Val::Vh(from), Val::Vh(to)) => Val::Vh(lerp(from, to)),
+        (Val::VMin(from), Val::VMin(to)) => Val::VMin(lerp(from, to)),
+        (Val::VMax(from), Val::VMax(to)) => Val::VMax(lerp(from, to)),
+        // FIXME auto and mixed units would have to be resolved to px first, so they flip halfway instead
+        _ => if t < 0.5 { from } else { to },
+    }
+}
+
+macro_rules! animatable {
+    ($($property:literal => $kind:ident($($field:tt)+)),* $(,)?) => {
+        // every property which can transition, except opacity and transform, see get() and set()
+        const ANIMATABLE: &[&str] = &[$($property),*];
+
+        fn get_field(computed: &ComputedStyle, property: &str) -> Option<Value> {
+            match property {
+                $($property => Some(Value::$kind(computed.$($field)+)),)*
+                _ => None,
+            }
+        }
+
+        fn set_field(computed: &mut ComputedStyle, property: &str, value: Value) {
+            match (property, value) {
+                $(($property, Value::$kind(value)) => computed.$($field)+ = value,)*
+                _ => {}
+            }
+        }
+    };
+}
+
+animatable! {
+    "color" => Color(text.color),
+    "background-color" => Color(background_color),
+    "border-color" => Color(border_color),
+    "outline-color" => Color(outline.color),
+    "outline-width" => Val(outline.width),
+    "outline-offset" => Val(outline.offset),
+    "font-size" => Number(text.font_size),
+    "width" => Val(style.width),
+    "height" => Val(style.height),
+    "min-width" => Val(style.min_width),
+    "min-height" => Val(style.min_height),
+    "max-width" => Val(style.max_width),
+    "max-height" => Val(style.max_height),
+    "top" => Val(style.top),
+    "right" => Val(style.right),
+    "bottom" => Val(style.bottom),
+    "left" => Val(style.left),
+    "margin-top" => Val(style.margin.top),
+    "margin-right" => Val(style.margin.right),
+    "margin-bottom" => Val(style.margin.bottom),
+    "margin-left" => Val(style.margin.left),
+    "padding-top" => Val(style.padding.top),
+    "padding-right" => Val(style.padding.right),
+    "padding-bottom" => Val(style.padding.bottom),
+    "padding-left" => Val(style.padding.left),
+    "border-top-width" => Val(style.border.top),
+    "border-right-width" => Val(style.border.right),
+    "border-bottom-width" => Val(style.border.bottom),
+    "border-left-width" => Val(style.border.left),
+    "border-top-left-radius" => Val(border_radius.top_left),
+    "border-top-right-radius" => Val(border_radius.top_right),
+    "border-bottom-right-radius" => Val(border_radius.bottom_right),
+    "border-bottom-left-radius" => Val(border_radius.bottom_left),
+    "flex-grow" => Number(style.flex_grow),
+    "flex-shrink" => Number(style.flex_shrink),
+    "flex-basis" => Val(style.flex_basis),
+    "row-gap" => Val(style.row_gap),
+    "column-gap" => Val(style.column_gap),
+}
+
+// every property which can transition or be animated by @keyframes
+pub(crate) fn properties() -> impl Iterator<Item = &'static str> {
+    ANIMATABLE.iter().copied().chain(["opacity", "transform"])
+}
+
+// opacity and transform are Options, so they can't go through the macro
+pub(crate) fn get(computed: &ComputedStyle, property: &str) -> Option<Value> {
+    match property {
+        "opacity" => Some(Value::Number(computed.effects.opacity.unwrap_or(1.))),
+        "transform" => Some(Value::Transform(computed.transform.transform.clone().unwrap_or_default())),
+        _ => get_field(computed, property),
+    }
+}
+
+pub(crate) fn set(computed: &mut ComputedStyle, property: &str, value: Value) {
+    match (property, value) {
+        ("opacity", Value::Number(opacity)) => computed.effects.opacity = Some(opacity),
+        ("transform", Value::Transform(functions)) => computed.transform.transform = Some(functions),
+        (_, value) => set_field(computed, property, value),
+    }
+}
+
+// a property on its way from one value to another
+#[derive(Clone, Debug)]
+pub(crate) struct Transition {
+    pub(crate) property: &'static str,
+    pub(crate) from: Value,
+    pub(crate) to: Value,
+    pub(crate) start: f32, // Time::elapsed_seconds, after the delay
+    pub(crate) duration: f32,
+    pub(crate) easing: CubicBezier,
+}
+
+impl Transition {
+    pub(crate) fn value(&self, now: f32) -> Value {
+        let progress = ((now - self.start) / self.duration).clamp(0., 1.);
+        self.from.interpolate(&self.to, self.easing.ease(progress))
+    }
+
+    fn is_finished(&self, now: f32) -> bool {
+        now >= self.start + self.duration
+    }
+}
+
+// the transitions of an element which are still running, added by css::restyle the first time it's styled
+#[derive(Component, Default)]
+pub(crate) struct Transitions(pub(crate) Vec<Transition>);
+
+impl Transitions {
+    // start a transition for every property which changed between two cascades and has a transition-duration,
+    // from wherever a transition of the same property had got to
+    pub(crate) fn start(&mut self, previous: &ComputedStyle, next: &ComputedStyle, now: f32) {
+        for property in properties() {
+            let (Some(from), Some(to)) = (get(previous, property), get(next, property)) else { continue; };
+
+            let running = self.0.iter().position(|transition| transition.property == property);
+            match running {
+                None if from == to => continue,
+                Some(index) if self.0[index].to == to => continue, // already on its way there
+                _ => {}
+            }
+
+            let from = running.map_or(from, |index| self.0.remove(index).value(now));
+            let Some((duration, easing, delay)) = next.transition.of(property) else { continue; };
+
+            if from != to {
+                self.0.push(Transition { property, from, to, start: now + delay, duration, easing });
+            }
+        }
+    }
+
+    // set every transitioning property to where it's got to, and forget the ones which are done,
+    // the last frame draws the final values so nothing needs to be drawn once they're gone
+    pub(crate) fn apply(&mut self, style: &mut ComputedStyle, now: f32) {
+        for transition in self.0.iter() {
+            set(style, transition.property, transition.value(now));
+        }
+
+        self.0.retain(|transition| !transition.is_finished(now));
+    }
+}